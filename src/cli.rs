@@ -1,23 +1,173 @@
 use std::env;
-use std::path::PathBuf;
 use std::process::exit;
 
-#[derive(Debug)]
+use crate::colorscheme::Color;
+use crate::input::{detect_wallpaper, stdin_has_data};
+
+#[derive(Debug, Clone, Copy, Hash)]
 pub enum Method {
     AreaAverage,
     KMeans,
     ANSI,
+    Material,
+    Vibrant,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     RGB,
     HEX,
+    HSL,
+    HSV,
+    FLOAT,
+    JSON,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rgb"   => Some(OutputFormat::RGB),
+            "hex"   => Some(OutputFormat::HEX),
+            "hsl"   => Some(OutputFormat::HSL),
+            "hsv"   => Some(OutputFormat::HSV),
+            "float" => Some(OutputFormat::FLOAT),
+            "json"  => Some(OutputFormat::JSON),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Theme {
+    Dark,
+    Light,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Sampling {
+    Grid,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Cvd {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Harmony {
+    Complementary,
+    Analogous,
+    Triadic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subcommand {
+    Generate,
+    Undeploy,
+    Lint,
+    Render,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Deploy {
+    Symlink,
+    Copy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Export {
+    Sequences,
+    Json,
+    Yaml,
+    Css,
+    Scss,
+    Xresources,
+    Shell,
+    Fish,
+    Base16,
+    Ase,
+    Termux,
+    WindowsTerminal,
+    Alacritty,
+    Kitty,
+    Foot,
+    WezTerm,
+    Konsole,
+    Gtk,
+    Qtct,
+    Dunst,
+    Vim,
+    VsCode,
+    Sway,
+    Hyprland,
+    Tmux,
+    Discord,
+    Pywalfox,
+    Polybar,
+    Btop,
+    Bat,
+    Delta,
+    Spicetify,
+}
+
+/// Region of the image to sample, in pixels or as a centered share of it.
+#[derive(Debug, Clone, Copy)]
+pub enum Crop {
+    Region(u32, u32, u32, u32),
+    Center(f32),
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Import {
+    Pywal,
+    Base16,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Bright {
+    Matched,
+    Derived,
 }
 
 pub struct Args{
     pub saturation: f32,
+    pub brightness: f32,
+    pub contrast: f32,
+    pub temperature: f32,
     pub method: Method,
+    pub theme: Theme,
+    pub bright: Bright,
+    pub pastel: bool,
+    pub duotone: bool,
+    pub harmony: Option<Harmony>,
+    pub high_contrast: bool,
+    pub ramps: u32,
+    pub cvd: Option<Cvd>,
+    pub background: Option<Color>,
+    pub foreground: Option<Color>,
+    pub sampling: Sampling,
+    pub frames: usize,
+    pub crop: Option<Crop>,
+    pub screen: bool,
+    pub import: Option<Import>,
+    pub seed: Option<Color>,
+    pub timeout: u64,
+    pub retries: u32,
+    pub max_redirects: u32,
+    pub min_chroma: u8,
+    pub distinctness: u32,
+    pub exclude_hues: Vec<(f32, f32)>,
+    pub only_hues: Vec<(f32, f32)>,
+    pub subcommand: Subcommand,
+    pub template: Option<String>,
+    pub deploy: Option<Deploy>,
+    pub exports: Vec<Export>,
+    pub dry_run: bool,
+    pub diff: bool,
     pub format: OutputFormat,
     pub verbose: bool,
     pub preview: bool,
@@ -27,7 +177,39 @@ impl Default for Args{
     fn default() -> Self {
         Self {
             saturation: 1.0,
+            brightness: 1.0,
+            contrast: 1.0,
+            temperature: 0.0,
             method: Method::AreaAverage,
+            theme: Theme::Dark,
+            bright: Bright::Matched,
+            pastel: false,
+            duotone: false,
+            harmony: None,
+            high_contrast: false,
+            ramps: 0,
+            cvd: None,
+            background: None,
+            foreground: None,
+            sampling: Sampling::Grid,
+            frames: 8,
+            crop: None,
+            screen: false,
+            import: None,
+            seed: None,
+            timeout: 30,
+            retries: 2,
+            max_redirects: 5,
+            min_chroma: 0,
+            distinctness: 50,
+            exclude_hues: Vec::new(),
+            only_hues: Vec::new(),
+            subcommand: Subcommand::Generate,
+            template: None,
+            deploy: None,
+            exports: Vec::new(),
+            dry_run: false,
+            diff: false,
             format: OutputFormat::HEX,
             verbose: false,
             preview: false,
@@ -36,39 +218,113 @@ impl Default for Args{
 }
 
 impl Args{
-    pub fn from_cli() -> (Args, String) {
+    pub fn from_cli() -> (Args, Vec<(String, f32)>) {
         let args: Vec<String> = env::args().collect();
         let program = &args[0];
 
-        if args.len() < 2 {
-            Self::usage(program);
-            eprintln!("Error: missing image path or url");
-            exit(1);
-        }
-
         let mut config = Args::default();
-        let mut image_uri = None;
+        let mut images = Vec::new();
         let mut i = 1;
 
+        match args.get(1).map(|a| a.as_str()) {
+            Some("undeploy") => (config.subcommand, i) = (Subcommand::Undeploy, 2),
+            Some("lint")     => (config.subcommand, i) = (Subcommand::Lint, 2),
+            Some("render")   => {
+                let Some(template) = args.get(2) else {
+                    Self::usage(program);
+                    eprintln!("Error: render needs a template path, or - for stdin");
+                    exit(1);
+                };
+                (config.subcommand, config.template, i) = (Subcommand::Render, Some(template.clone()), 3);
+            }
+            _ => {}
+        }
+
         while i < args.len() {
             let arg = &args[i];
 
-            if arg.starts_with('-') {
-                i = Self::parse_flag(&arg, &args, i, &mut config, &program);
+            if arg.starts_with('-') && arg != "-" {
+                i = Self::parse_flag(arg, &args, i, &mut config, program);
                 continue;
             }
 
-            if image_uri.is_none() {
-                image_uri = Some(arg.clone());
-                i += 1;
-                continue;
-            }
+            images.push(Self::parse_weighted_image(arg).unwrap_or_else(|| {
+                Self::usage(program);
+                eprintln!("Error: invalid image weight in '{}'", arg);
+                exit(1);
+            }));
+            i += 1;
+        }
+
+        if matches!(config.subcommand, Subcommand::Undeploy | Subcommand::Lint) {
+            return (config, images)
+        }
+
+        if config.import.is_some() && images.len() != 1 {
+            Self::usage(program);
+            eprintln!("Error: --import takes exactly one scheme file");
+            exit(1);
+        }
+
+        if config.screen && !images.is_empty() {
+            Self::usage(program);
+            eprintln!("Error: --screen does not take an image path");
+            exit(1);
+        }
+
+        if config.seed.is_some() && !images.is_empty() {
+            Self::usage(program);
+            eprintln!("Error: --from-color does not take an image path");
+            exit(1);
+        }
+
+        let template_from_stdin = config.template.as_deref() == Some("-");
+        if template_from_stdin && images.iter().any(|(uri, _)| uri == "-") {
             Self::usage(program);
-            eprintln!("Error: unexpected argument '{}'", arg);
+            eprintln!("Error: the template and the image can't both come from stdin");
             exit(1);
         }
 
-        (config, image_uri.unwrap())
+        // Without an image, render uses the current scheme
+        if images.is_empty() && config.subcommand == Subcommand::Render && !config.screen && config.seed.is_none() {
+            return (config, images)
+        }
+
+        // Data piped in without a path is read as the image, like `-`,
+        // otherwise the current wallpaper is used
+        if images.is_empty() {
+            let image_uri = if config.screen {
+                "screen".to_string()
+            } else if config.seed.is_some() {
+                "seed".to_string()
+            } else if stdin_has_data() {
+                "-".to_string()
+            } else {
+                detect_wallpaper().unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: missing image path or url, and no wallpaper was found");
+                    exit(1);
+                })
+            };
+            images.push((image_uri, 1.0));
+        }
+
+        (config, images)
+    }
+
+    /// Splits an optional `:weight` suffix off an image, e.g. `a.png:0.7`.
+    /// Suffixes that aren't numbers are part of the path, and so is a port
+    /// right after a url's host (`https://example.com:8080`).
+    fn parse_weighted_image(arg: &str) -> Option<(String, f32)> {
+        let is_authority = |uri: &str| uri.split_once("://").is_some_and(|(_, rest)| !rest.contains('/'));
+        let weighted = arg.rsplit_once(':')
+            .filter(|(uri, _)| !uri.is_empty() && !is_authority(uri))
+            .and_then(|(uri, weight)| Some((uri, weight.parse::<f32>().ok()?)));
+        match weighted {
+            Some((uri, weight)) if weight.is_finite() && weight > 0.0 => Some((uri.to_string(), weight)),
+            Some(_) => None,
+            None    => Some((arg.to_string(), 1.0)),
+        }
     }
 
     fn parse_flag(arg: &str, args: &[String], i: usize, config: &mut Args, program: &str) -> usize {
@@ -94,11 +350,46 @@ impl Args{
                     });
                 i + 2
             }
+            "-b" | "--brightness" => {
+                config.brightness = next_arg()
+                    .unwrap()
+                    .parse::<f32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid brightness value '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "-c" | "--contrast" => {
+                config.contrast = next_arg()
+                    .unwrap()
+                    .parse::<f32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid contrast value '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--temperature" => {
+                config.temperature = next_arg()
+                    .unwrap()
+                    .parse::<f32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid temperature value '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
             "-m" | "--method" => {
                 config.method = match next_arg().unwrap().as_str() {
                     "area_average" | "aa" => Method::AreaAverage,
                     "kmeans" | "km"       => Method::KMeans,
                     "ansi" | "an"         => Method::ANSI,
+                    "material" | "mt"     => Method::Material,
+                    "vibrant" | "vb"      => Method::Vibrant,
                     _ => {
                         Self::usage(program);
                         eprintln!("Error: unknown method '{}'", next_arg().unwrap());
@@ -107,18 +398,299 @@ impl Args{
                 };
                 i + 2
             }
-            "-f" | "--format" => {
-                config.format = match next_arg().unwrap().as_str() {
-                    "rgb" => OutputFormat::RGB,
-                    "hex" => OutputFormat::HEX,
+            "--samples" => {
+                config.sampling = match next_arg().unwrap().as_str() {
+                    "grid" => Sampling::Grid,
+                    "full" => Sampling::Full,
                     _ => {
                         Self::usage(program);
-                        eprintln!("Error: unknown format '{}'", next_arg().unwrap());
+                        eprintln!("Error: unknown sampling mode '{}'", next_arg().unwrap());
                         exit(1);
                     }
                 };
                 i + 2
             }
+            "--screen" => {
+                config.screen = true;
+                i + 1
+            }
+            "--from-color" => {
+                config.seed = Some(Color::from_hex(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: invalid seed color '{}'", next_arg().unwrap());
+                    exit(1);
+                }));
+                i + 2
+            }
+            "--import" => {
+                config.import = Some(match next_arg().unwrap().as_str() {
+                    "pywal"             => Import::Pywal,
+                    "base16" | "base24" => Import::Base16,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown import format '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                });
+                i + 2
+            }
+            "--frames" => {
+                config.frames = next_arg()
+                    .unwrap()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .unwrap_or_else(|| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid frame count '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--timeout" => {
+                config.timeout = next_arg()
+                    .unwrap()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .unwrap_or_else(|| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid timeout '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--retries" => {
+                config.retries = next_arg()
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid retry count '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--max-redirects" => {
+                config.max_redirects = next_arg()
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid redirect limit '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--crop" => {
+                config.crop = Some(Self::parse_crop(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: invalid crop '{}'", next_arg().unwrap());
+                    exit(1);
+                }));
+                i + 2
+            }
+            "--min-chroma" => {
+                config.min_chroma = next_arg()
+                    .unwrap()
+                    .parse::<u8>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid min chroma value '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--distinctness" => {
+                config.distinctness = next_arg()
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid distinctness value '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--exclude-hue" | "--only-hue" => {
+                let ranges = Self::parse_hue_ranges(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: invalid hue range '{}'", next_arg().unwrap());
+                    exit(1);
+                });
+                if arg == "--exclude-hue" {
+                    config.exclude_hues.extend(ranges);
+                } else {
+                    config.only_hues.extend(ranges);
+                }
+                i + 2
+            }
+            "-t" | "--theme" => {
+                config.theme = match next_arg().unwrap().as_str() {
+                    "dark"  => Theme::Dark,
+                    "light" => Theme::Light,
+                    "auto"  => Theme::Auto,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown theme '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                };
+                i + 2
+            }
+            "--bright" => {
+                config.bright = match next_arg().unwrap().as_str() {
+                    "matched" => Bright::Matched,
+                    "derived" => Bright::Derived,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown bright mode '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                };
+                i + 2
+            }
+            "--ramps" => {
+                config.ramps = next_arg()
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| {
+                        Self::usage(program);
+                        eprintln!("Error: invalid ramp step count '{}'", next_arg().unwrap());
+                        exit(1);
+                    });
+                i + 2
+            }
+            "--cvd" => {
+                config.cvd = Some(match next_arg().unwrap().as_str() {
+                    "protanopia"   => Cvd::Protanopia,
+                    "deuteranopia" => Cvd::Deuteranopia,
+                    "tritanopia"   => Cvd::Tritanopia,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown color vision deficiency '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                });
+                i + 2
+            }
+            "--bg" => {
+                config.background = Some(Color::from_hex(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: invalid background color '{}'", next_arg().unwrap());
+                    exit(1);
+                }));
+                i + 2
+            }
+            "--fg" => {
+                config.foreground = Some(Color::from_hex(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: invalid foreground color '{}'", next_arg().unwrap());
+                    exit(1);
+                }));
+                i + 2
+            }
+            "-f" | "--format" => {
+                config.format = OutputFormat::from_name(next_arg().unwrap()).unwrap_or_else(|| {
+                    Self::usage(program);
+                    eprintln!("Error: unknown format '{}'", next_arg().unwrap());
+                    exit(1);
+                });
+                i + 2
+            }
+            "--pastel" => {
+                config.pastel = true;
+                i + 1
+            }
+            "--duotone" => {
+                config.duotone = true;
+                i + 1
+            }
+            "--harmonize" => {
+                config.harmony = Some(match next_arg().unwrap().as_str() {
+                    "complementary" => Harmony::Complementary,
+                    "analogous"     => Harmony::Analogous,
+                    "triadic"       => Harmony::Triadic,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown harmony '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                });
+                i + 2
+            }
+            "--high-contrast" => {
+                config.high_contrast = true;
+                i + 1
+            }
+            "--deploy" => {
+                config.deploy = Some(match next_arg().unwrap().as_str() {
+                    "symlink" => Deploy::Symlink,
+                    "copy"    => Deploy::Copy,
+                    _ => {
+                        Self::usage(program);
+                        eprintln!("Error: unknown deploy mode '{}'", next_arg().unwrap());
+                        exit(1);
+                    }
+                });
+                i + 2
+            }
+            "--export" => {
+                for name in next_arg().unwrap().split(',') {
+                    let kind = match name.trim() {
+                        "sequences"        => Export::Sequences,
+                        "json"             => Export::Json,
+                        "yaml"             => Export::Yaml,
+                        "css"              => Export::Css,
+                        "scss"             => Export::Scss,
+                        "xresources"       => Export::Xresources,
+                        "sh"               => Export::Shell,
+                        "fish"             => Export::Fish,
+                        "base16"           => Export::Base16,
+                        "ase"              => Export::Ase,
+                        "termux"           => Export::Termux,
+                        "windows-terminal" => Export::WindowsTerminal,
+                        "alacritty"        => Export::Alacritty,
+                        "kitty"            => Export::Kitty,
+                        "foot"             => Export::Foot,
+                        "wezterm"          => Export::WezTerm,
+                        "konsole"          => Export::Konsole,
+                        "gtk"              => Export::Gtk,
+                        "qt"               => Export::Qtct,
+                        "dunst"            => Export::Dunst,
+                        "vim"              => Export::Vim,
+                        "vscode"           => Export::VsCode,
+                        "sway"             => Export::Sway,
+                        "hyprland"         => Export::Hyprland,
+                        "tmux"             => Export::Tmux,
+                        "discord"          => Export::Discord,
+                        "pywalfox"         => Export::Pywalfox,
+                        "polybar"          => Export::Polybar,
+                        "btop"             => Export::Btop,
+                        "bat"              => Export::Bat,
+                        "delta"            => Export::Delta,
+                        "spicetify"        => Export::Spicetify,
+                        _ => {
+                            Self::usage(program);
+                            eprintln!("Error: unknown export '{}'", name);
+                            exit(1);
+                        }
+                    };
+                    if !config.exports.contains(&kind) {
+                        config.exports.push(kind);
+                    }
+                }
+                i + 2
+            }
+            "--dry-run" => {
+                config.dry_run = true;
+                i + 1
+            }
+            "--diff" => {
+                config.dry_run = true;
+                config.diff = true;
+                i + 1
+            }
             "-v" | "--verbose" => {
                 config.verbose = true;
                 i + 1
@@ -135,13 +707,91 @@ impl Args{
         }
     }
 
+    /// Parses `x,y,w,h` in pixels or `center:<percent>%`.
+    fn parse_crop(value: &str) -> Option<Crop> {
+        if let Some(share) = value.strip_prefix("center:") {
+            let share = share.trim_end_matches('%').parse::<f32>().ok()?;
+            return (share > 0.0 && share <= 100.0).then_some(Crop::Center(share / 100.0))
+        }
+
+        let parts: Vec<u32> = value.split(',').map(|p| p.trim().parse::<u32>().ok()).collect::<Option<_>>()?;
+        match parts[..] {
+            [x, y, w, h] if w > 0 && h > 0 => Some(Crop::Region(x, y, w, h)),
+            _ => None,
+        }
+    }
+
+    /// Parses comma separated `from-to` degree ranges, e.g. `20-60,330-10`.
+    fn parse_hue_ranges(value: &str) -> Option<Vec<(f32, f32)>> {
+        value.split(',')
+            .map(|range| {
+                let (from, to) = range.split_once('-')?;
+                let from = from.trim().parse::<f32>().ok()?;
+                let to = to.trim().parse::<f32>().ok()?;
+                Some((from.rem_euclid(360.0), to.rem_euclid(360.0)))
+            })
+            .collect()
+    }
+
     fn usage(program: &str) {
-        eprintln!("Usage {program} [-s][-m][-f][-v] <path or url>");
+        eprintln!("Usage {program} [-s][-b][-c][-m][-t][-f][-v] [<path, url or - for stdin>[:weight] ...]");
+        eprintln!("       {program} undeploy    restore the files replaced by --deploy");
+        eprintln!("       {program} render <template or -> [<image> ...]  print a template filled in with the image's scheme");
+        eprintln!("       {program} lint        check templates for unknown placeholders and unclosed blocks");
         eprintln!("Arguments:");
         eprintln!("     -s | --saturation   <float>");
-        eprintln!("     -m | --method       [area_average(aa) / kmeans(km) / ansi(an)]");
-        eprintln!("     -f | --format       [rgb/hex]");
+        eprintln!("     -b | --brightness   <float>");
+        eprintln!("     -c | --contrast     <float> palette lightness spread around the background");
+        eprintln!("          --temperature  <float> warm (positive) or cool (negative) shift, about -20 to 20");
+        eprintln!("     -m | --method       [area_average(aa) / kmeans(km) / ansi(an) / material(mt) / vibrant(vb)]");
+        eprintln!("          --samples      [grid/full] full uses every pixel of a 256x256 downscale");
+        eprintln!("          --screen       theme from a screenshot instead of an image (grim/maim/import/scrot)");
+        eprintln!("          --from-color   <#rrggbb> build the scheme around one color instead of an image");
+        eprintln!("          --import       [pywal/base16/base24] <path> use a scheme saved by another tool instead of an image");
+        eprintln!("          --frames       <int> frames sampled from animated images (default 8)");
+        eprintln!("          --timeout      <secs> timeout for downloading images (default 30)");
+        eprintln!("          --retries      <int> retries after a failed download (default 2)");
+        eprintln!("          --max-redirects <int> redirects followed when downloading, 0 disables (default 5)");
+        eprintln!("          --crop         <x,y,w,h | center:<percent>%> only sample this region");
+        eprintln!("          --min-chroma   <0-255> exclude greyer samples from the palette");
+        eprintln!("          --distinctness <int> starting color distance for area_average (default 50)");
+        eprintln!("          --exclude-hue  <from-to,...> drop samples in these hue ranges");
+        eprintln!("          --only-hue     <from-to,...> keep only samples in these hue ranges");
+        eprintln!("     -t | --theme        [dark/light/auto]");
+        eprintln!("          --bright       [matched/derived] derive colors 8-15 from 0-7");
+        eprintln!("          --pastel       soften palette colors into pastels");
+        eprintln!("          --duotone      build the palette from the two dominant hues");
+        eprintln!("          --harmonize    [complementary/analogous/triadic] snap hues to the dominant one");
+        eprintln!("          --high-contrast keep every color at 4.5:1 contrast or more against the background");
+        eprintln!("          --ramps        <int> tints and shades per palette color, @color<i>_l<k> / _d<k>");
+        eprintln!("          --cvd          [protanopia/deuteranopia/tritanopia] keep colors distinguishable");
+        eprintln!("          --bg           <#rrggbb> use this background instead of the extracted one");
+        eprintln!("          --fg           <#rrggbb> use this foreground instead of the extracted one");
+        eprintln!("     -f | --format       [rgb/hex/hsl/hsv/float/json] json prints the whole scheme with -v");
+        eprintln!("          --deploy       [symlink/copy] put targets.toml destinations in place, backing up what was there");
+        eprintln!("          --export       [sequences/json/yaml/css/scss/xresources/sh/fish/base16/ase/termux/windows-terminal/alacritty/kitty/foot/wezterm/konsole/gtk/qt/dunst/vim/vscode/sway/hyprland/tmux/discord/pywalfox/polybar/btop/bat/delta/spicetify],... built-in outputs to write next to the templates");
+        eprintln!("          --dry-run      list the files templates would create or change, without writing anything");
+        eprintln!("          --diff         like --dry-run, also showing the changes (needs diff)");
         eprintln!("     -v | --verbose      print colors to stdout");
         eprintln!("     -p | --preview      if passed, won't generate templates");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_images() {
+        assert_eq!(Args::parse_weighted_image("a.png:0.7"), Some(("a.png".to_string(), 0.7)));
+        assert_eq!(Args::parse_weighted_image("https://example.com/a.png:2"), Some(("https://example.com/a.png".to_string(), 2.0)));
+        assert_eq!(Args::parse_weighted_image("a.png:0"), None);
+    }
+
+    #[test]
+    fn url_port_is_not_a_weight() {
+        for url in ["https://example.com:8080", "http://localhost:8000", "https://example.com:8080/a.png"] {
+            assert_eq!(Args::parse_weighted_image(url), Some((url.to_string(), 1.0)));
+        }
+    }
+}
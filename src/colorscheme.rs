@@ -1,4 +1,8 @@
+use std::cmp::Reverse;
 use image::{DynamicImage, Rgba, GenericImageView};
+use image::imageops::FilterType;
+
+use crate::cli::{Cvd, Harmony};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Color {
@@ -24,6 +28,17 @@ impl Color {
         }
     }
 
+    /// Parses `#rrggbb` or `rrggbb`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() { return None }
+
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        return Some(Self::from_rgba(Rgba([r, g, b, 255])))
+    }
+
     pub fn distance_to(&self, other: &Self) -> f32 {
         let dr = self.r as i32 - other.r as i32;
         let dg = self.g as i32 - other.g as i32;
@@ -32,43 +47,854 @@ impl Color {
         return ((dr * dr + dg * dg + db * db) as f32).sqrt()
     }
 
-    pub fn with_saturation(mut self, saturation: f32) -> Self {
+    /// Scales OKLCH chroma. Colors pushed out of sRGB are brought back by
+    /// lowering chroma at the same lightness and hue instead of clipping channels.
+    pub fn with_saturation(self, saturation: f32) -> Self {
         if saturation == 1.0 || self.chroma == 0 { return self; }
-        
-        let gray = (self.luminance * 255.0) as f32;
 
-        self.r = (gray + (self.r as f32 - gray) * saturation).clamp(0.0, 255.0) as u8;
-        self.g = (gray + (self.g as f32 - gray) * saturation).clamp(0.0, 255.0) as u8;
-        self.b = (gray + (self.b as f32 - gray) * saturation).clamp(0.0, 255.0) as u8;
-        
-        let max = self.r.max(self.g).max(self.b);
-        let min = self.r.min(self.g).min(self.b);
-        self.chroma = max - min;
-        self.luminance = (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0;
-        
-        return self
+        let (l, c, h) = self.oklch();
+        return Self::from_oklch(l, (c * saturation).max(0.0), h)
+    }
+
+    /// Scales CIE L* lightness, keeping hue and chroma where sRGB allows it.
+    pub fn with_brightness(self, brightness: f32) -> Self {
+        if brightness == 1.0 { return self; }
+
+        let (l, c, h) = self.lch();
+        return Self::from_lch((l * brightness).clamp(0.0, 100.0), c, h)
+    }
+
+    /// Moves the color along the blue-orange axis in Lab, positive values warm it up.
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        if temperature == 0.0 { return self; }
+
+        let (l, c, h) = self.lch();
+        let a = c * h.to_radians().cos() + temperature * 0.25;
+        let b = c * h.to_radians().sin() + temperature;
+        return Self::from_lch(l, (a * a + b * b).sqrt(), b.atan2(a).to_degrees().rem_euclid(360.0))
+    }
+
+    /// OKLCh as (lightness 0-1, chroma, hue in degrees).
+    pub fn oklch(&self) -> (f32, f32, f32) {
+        let r = srgb_to_linear(self.r);
+        let g = srgb_to_linear(self.g);
+        let b = srgb_to_linear(self.b);
+
+        let l = (0.4122215 * r + 0.5363326 * g + 0.051446 * b).cbrt();
+        let m = (0.2119035 * r + 0.6806995 * g + 0.107397 * b).cbrt();
+        let s = (0.08830246 * r + 0.2817189 * g + 0.6299787 * b).cbrt();
+
+        let ok_l = 0.2104543 * l + 0.7936178 * m - 0.004072047 * s;
+        let ok_a = 1.977998 * l - 2.428592 * m + 0.4505937 * s;
+        let ok_b = 0.02590404 * l + 0.7827718 * m - 0.8086758 * s;
+
+        return (ok_l, (ok_a * ok_a + ok_b * ok_b).sqrt(), ok_b.atan2(ok_a).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Inverse of `oklch`; chroma is reduced until the color fits in sRGB.
+    pub fn from_oklch(l: f32, c: f32, h: f32) -> Self {
+        if let Some(rgb) = oklch_to_srgb(l, c, h) {
+            return Self::from_rgba(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+        }
+
+        let mut lo = 0.0;
+        let mut hi = c;
+        for _ in 0..16 {
+            let mid = (lo + hi) / 2.0;
+            if oklch_to_srgb(l, mid, h).is_some() { lo = mid } else { hi = mid }
+        }
+        let rgb = oklch_to_srgb(l, lo, h).unwrap_or([0, 0, 0]);
+        return Self::from_rgba(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+    }
+
+    /// Lightened and slightly saturated variant, the bright counterpart of an ANSI color.
+    pub fn brightened(&self) -> Self {
+        let (h, s, l) = self.hsl();
+        return Color::from_hsl(h, (s * 1.15).min(1.0), (l + 0.15).min(0.9))
+    }
+
+    /// Linear blend in sRGB, `t` = 0.0 gives `self` and 1.0 gives `other`.
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round().clamp(0.0, 255.0) as u8;
+        return Self::from_rgba(Rgba([lerp(self.r, other.r), lerp(self.g, other.g), lerp(self.b, other.b), 255]))
+    }
+
+    /// WCAG relative luminance.
+    pub fn relative_luminance(&self) -> f32 {
+        return 0.2126 * srgb_to_linear(self.r) + 0.7152 * srgb_to_linear(self.g) + 0.0722 * srgb_to_linear(self.b)
+    }
+
+    /// WCAG contrast ratio, from 1.0 to 21.0.
+    pub fn contrast_ratio(&self, other: &Self) -> f32 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+        return (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Moves L* away from `background` until the WCAG contrast ratio reaches `min_ratio`,
+    /// falling back to black or white when the hue can't get there.
+    pub fn with_min_contrast(self, background: &Self, min_ratio: f32) -> Self {
+        const STEP: f32 = 2.0;
+
+        if self.contrast_ratio(background) >= min_ratio { return self; }
+
+        let white = Color::from_rgba(Rgba([255, 255, 255, 255]));
+        let black = Color::from_rgba(Rgba([0, 0, 0, 255]));
+        let lighten = white.contrast_ratio(background) >= black.contrast_ratio(background);
+
+        let (mut l, c, h) = self.lch();
+        while (0.0..=100.0).contains(&l) {
+            l += if lighten { STEP } else { -STEP };
+            let candidate = Color::from_lch(l.clamp(0.0, 100.0), c, h);
+            if candidate.contrast_ratio(background) >= min_ratio { return candidate; }
+        }
+        return if lighten { white } else { black }
+    }
+
+    /// CIE76 color difference.
+    pub fn delta_e(&self, other: &Self) -> f32 {
+        let (l1, c1, h1) = self.lch();
+        let (l2, c2, h2) = other.lch();
+        let da = c1 * h1.to_radians().cos() - c2 * h2.to_radians().cos();
+        let db = c1 * h1.to_radians().sin() - c2 * h2.to_radians().sin();
+        return ((l1 - l2).powi(2) + da * da + db * db).sqrt()
+    }
+
+    /// How the color looks with the given deficiency (Machado et al. 2009, full severity).
+    pub fn simulate_cvd(&self, cvd: Cvd) -> Self {
+        let m: [[f32; 3]; 3] = match cvd {
+            Cvd::Protanopia   => [[ 0.152286,  1.052583, -0.204868],
+                                  [ 0.114503,  0.786281,  0.099216],
+                                  [-0.003882, -0.048116,  1.051998]],
+            Cvd::Deuteranopia => [[ 0.367322,  0.860646, -0.227968],
+                                  [ 0.280085,  0.672501,  0.047413],
+                                  [-0.011820,  0.042940,  0.968881]],
+            Cvd::Tritanopia   => [[ 1.255528, -0.076749, -0.178779],
+                                  [-0.078411,  0.930809,  0.147602],
+                                  [ 0.004733,  0.691367,  0.303900]],
+        };
+        let lin = [srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b)];
+        let to_u8 = |row: [f32; 3]| {
+            let v = row[0] * lin[0] + row[1] * lin[1] + row[2] * lin[2];
+            (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0).round() as u8
+        };
+        return Self::from_rgba(Rgba([to_u8(m[0]), to_u8(m[1]), to_u8(m[2]), 255]))
+    }
+
+    /// HSL as (hue in degrees, saturation 0-1, lightness 0-1).
+    pub fn hsl(&self) -> (f32, f32, f32) {
+        let r = self.r as f32 / 255.0;
+        let g = self.g as f32 / 255.0;
+        let b = self.b as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        if d == 0.0 { return (0.0, 0.0, l) }
+
+        let s = d / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / d + 2.0
+        } else {
+            (r - g) / d + 4.0
+        };
+        return (h * 60.0, s.clamp(0.0, 1.0), l)
+    }
+
+    /// HSV as (hue in degrees, saturation 0-1, value 0-1).
+    pub fn hsv(&self) -> (f32, f32, f32) {
+        let (h, s, l) = self.hsl();
+        let v = l + s * l.min(1.0 - l);
+        let sv = if v == 0.0 { 0.0 } else { 2.0 * (1.0 - l / v) };
+        return (h, sv, v)
     }
+
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let hp = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (hp.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match hp as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = l - c / 2.0;
+        let to_u8 = |v: f32| ((v + m).clamp(0.0, 1.0) * 255.0).round() as u8;
+        return Self::from_rgba(Rgba([to_u8(r), to_u8(g), to_u8(b), 255]))
+    }
+
+    /// CIE LCh(ab) as (lightness 0-100, chroma, hue in degrees).
+    pub fn lch(&self) -> (f32, f32, f32) {
+        let r = srgb_to_linear(self.r);
+        let g = srgb_to_linear(self.g);
+        let b = srgb_to_linear(self.b);
+
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+        let y =  0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+        let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+
+        let l = 116.0 * fy - 16.0;
+        let a = 500.0 * (fx - fy);
+        let bb = 200.0 * (fy - fz);
+
+        return (l, (a * a + bb * bb).sqrt(), bb.atan2(a).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Inverse of `lch`; chroma is reduced until the color fits in sRGB.
+    pub fn from_lch(l: f32, c: f32, h: f32) -> Self {
+        if let Some(rgb) = lch_to_srgb(l, c, h) {
+            return Self::from_rgba(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+        }
+
+        let mut lo = 0.0;
+        let mut hi = c;
+        for _ in 0..16 {
+            let mid = (lo + hi) / 2.0;
+            if lch_to_srgb(l, mid, h).is_some() { lo = mid } else { hi = mid }
+        }
+        let rgb = lch_to_srgb(l, lo, h).unwrap_or([0, 0, 0]);
+        return Self::from_rgba(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+    }
+}
+
+/// Shortest angle between two hues, in degrees.
+pub fn hue_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(360.0);
+    return d.min(360.0 - d)
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn linear_to_u8(rgb: [f32; 3]) -> Option<[u8; 3]> {
+    const EPS: f32 = 0.0001;
+    let mut out = [0u8; 3];
+    for (o, v) in out.iter_mut().zip(rgb) {
+        if !(-EPS..=1.0 + EPS).contains(&v) { return None }
+        *o = (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    }
+    return Some(out)
+}
+
+fn oklch_to_srgb(l: f32, c: f32, h: f32) -> Option<[u8; 3]> {
+    let a = c * h.to_radians().cos();
+    let b = c * h.to_radians().sin();
+
+    let l_ = (l + 0.3963378 * a + 0.2158038 * b).powi(3);
+    let m_ = (l - 0.1055613 * a - 0.06385417 * b).powi(3);
+    let s_ = (l - 0.08948418 * a - 1.291486 * b).powi(3);
+
+    return linear_to_u8([
+         4.076742 * l_ - 3.307712 * m_ + 0.2309699 * s_,
+        -1.268438 * l_ + 2.609757 * m_ - 0.3413194 * s_,
+        -0.004196086 * l_ - 0.7034186 * m_ + 1.707615 * s_,
+    ])
+}
+
+fn lch_to_srgb(l: f32, c: f32, h: f32) -> Option<[u8; 3]> {
+    let a = c * h.to_radians().cos();
+    let b = c * h.to_radians().sin();
+
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let finv = |t: f32| if t > 0.206893 { t * t * t } else { (t - 16.0 / 116.0) / 7.787 };
+
+    let x = finv(fx) * 0.95047;
+    let y = finv(fy);
+    let z = finv(fz) * 1.08883;
+
+    return linear_to_u8([
+         3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+         0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ])
 }
 
+#[derive(Clone)]
 pub struct Colorscheme {
     pub palette: Vec<Color>,
     pub background: Color, 
     pub foreground: Color,
+    pub named: Vec<(String, Color)>,
+    pub monochrome: bool,
 }
 
 impl Colorscheme {
-    pub fn with_saturation(self, saturation: f32) -> Self {
+    fn map_colors(self, f: impl Fn(Color) -> Color) -> Self {
         Self {
             palette: self.palette
                     .into_iter()
-                    .map(|c| c.with_saturation(saturation))
+                    .map(&f)
+                    .collect(),
+            background: f(self.background),
+            foreground: f(self.foreground),
+            named: self.named
+                    .into_iter()
+                    .map(|(name, c)| (name, f(c)))
                     .collect(),
-            background: self.background.with_saturation(saturation),
-            foreground: self.foreground.with_saturation(saturation),
+            monochrome: self.monochrome,
+        }
+    }
+
+    pub fn with_saturation(self, saturation: f32) -> Self {
+        return self.map_colors(|c| c.with_saturation(saturation))
+    }
+
+    pub fn with_brightness(self, brightness: f32) -> Self {
+        return self.map_colors(|c| c.with_brightness(brightness))
+    }
+
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        return self.map_colors(|c| c.with_temperature(temperature))
+    }
+
+    /// Pulls palette and role colors towards high lightness and moderate chroma,
+    /// keeping their hue. Background and foreground are left alone.
+    pub fn with_pastel(mut self) -> Self {
+        const TARGET_L:   f32 = 0.85;
+        const MAX_CHROMA: f32 = 0.09;
+
+        let pastel = |c: Color| {
+            let (l, chroma, hue) = c.oklch();
+            Color::from_oklch(l + (TARGET_L - l) * 0.7, (chroma * 0.6).min(MAX_CHROMA), hue)
+        };
+        self.palette = self.palette.into_iter().map(pastel).collect();
+        self.named = self.named.into_iter().map(|(name, c)| (name, pastel(c))).collect();
+        return self
+    }
+
+    /// Hue bin centers of the chromatic palette colors, most dominant first.
+    fn dominant_hues(&self) -> Vec<f32> {
+        const HUE_BINS: usize = 12;
+
+        let mut weights = [0f32; HUE_BINS];
+        for (h, s, _l) in self.palette.iter().map(|c| c.hsl()) {
+            if s <= 0.1 { continue; }
+            weights[(h / 360.0 * HUE_BINS as f32) as usize % HUE_BINS] += s;
+        }
+        let mut bins: Vec<usize> = (0..HUE_BINS).filter(|&b| weights[b] > 0.0).collect();
+        bins.sort_unstable_by(|&a, &b| weights[b].total_cmp(&weights[a]));
+
+        return bins.into_iter().map(|b| (b as f32 + 0.5) * 360.0 / HUE_BINS as f32).collect()
+    }
+
+    /// Rebuilds the palette from its two dominant hues, alternating between
+    /// them while stepping from shades to tints.
+    pub fn with_duotone(mut self) -> Self {
+        const MIN_HUE_DISTANCE: f32 = 60.0;
+
+        let hues = self.dominant_hues();
+        let Some(&first) = hues.first() else {
+            return self;
+        };
+        let second = hues
+                .iter()
+                .copied()
+                .find(|&h| hue_distance(h, first) >= MIN_HUE_DISTANCE)
+                .unwrap_or((first + 180.0).rem_euclid(360.0));
+
+        let chromatic: Vec<f32> = self.palette.iter().map(|c| c.hsl().1).filter(|&s| s > 0.1).collect();
+        let saturation = chromatic.iter().sum::<f32>() / chromatic.len() as f32;
+        let count = self.palette.len().max(16);
+        self.palette = (0..count)
+                .map(|i| {
+                    let hue = if i % 2 == 0 { first } else { second };
+                    let lightness = 0.25 + 0.6 * i as f32 / (count - 1) as f32;
+                    Color::from_hsl(hue, saturation, lightness)
+                })
+                .collect();
+        return self
+    }
+
+    /// Pulls palette and role hues most of the way towards the nearest hue
+    /// in a harmonic relationship with the dominant one.
+    pub fn with_harmony(mut self, harmony: Harmony) -> Self {
+        const STRENGTH: f32 = 0.75;
+
+        let Some(&dominant) = self.dominant_hues().first() else {
+            return self;
+        };
+        let offsets: &[f32] = match harmony {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::Analogous     => &[-30.0, 0.0, 30.0],
+            Harmony::Triadic       => &[0.0, 120.0, 240.0],
+        };
+
+        let harmonize = |c: Color| {
+            let (h, s, l) = c.hsl();
+            if s <= 0.1 { return c; }
+
+            let target = offsets
+                    .iter()
+                    .map(|o| (dominant + o).rem_euclid(360.0))
+                    .min_by(|a, b| hue_distance(h, *a).total_cmp(&hue_distance(h, *b)))
+                    .unwrap_or(h);
+            let delta = ((target - h + 540.0) % 360.0) - 180.0;
+            Color::from_hsl(h + delta * STRENGTH, s, l)
+        };
+        self.palette = self.palette.into_iter().map(harmonize).collect();
+        self.named = self.named.into_iter().map(|(name, c)| (name, harmonize(c))).collect();
+        return self
+    }
+
+    /// Palette color closest to `hue`, if one is within `window` degrees and
+    /// colorful enough for its hue to mean anything.
+    pub fn closest_to_hue(&self, hue: f32, window: f32) -> Option<Color> {
+        const MIN_SATURATION: f32 = 0.25;
+
+        return self.palette
+                .iter()
+                .filter(|c| c.hsl().1 >= MIN_SATURATION)
+                .map(|c| (*c, hue_distance(c.hsl().0, hue)))
+                .filter(|&(_, d)| d <= window)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(c, _)| c)
+    }
+
+    /// Color for `hue`: the closest palette member, or one synthesized at the
+    /// palette's average saturation and lightness when nothing is close.
+    pub fn color_for_hue(&self, hue: f32) -> Color {
+        const WINDOW: f32 = 35.0;
+
+        if let Some(c) = self.closest_to_hue(hue, WINDOW) { return c; }
+
+        let chromatic: Vec<(f32, f32, f32)> = self.palette.iter().map(|c| c.hsl()).filter(|c| c.1 > 0.1).collect();
+        let (s, l) = if chromatic.is_empty() {
+            (0.6, 0.55)
+        } else {
+            let n = chromatic.len() as f32;
+            (chromatic.iter().map(|c| c.1).sum::<f32>() / n, chromatic.iter().map(|c| c.2).sum::<f32>() / n)
+        };
+        return Color::from_hsl(hue, s.max(0.5), l.clamp(0.45, 0.65))
+    }
+
+    /// Adds `error`, `warning`, `success` and `info` roles.
+    pub fn with_semantic_roles(mut self) -> Self {
+        const ROLES: [(&str, f32); 4] = [("error", 5.0), ("warning", 40.0), ("success", 120.0), ("info", 215.0)];
+
+        for (name, hue) in ROLES {
+            let c = self.color_for_hue(hue);
+            self.named.push((name.to_string(), c));
+        }
+        return self
+    }
+
+    /// Adds `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan` and
+    /// `white`: ANSI slots 0-7 when `ansi` is set, otherwise the palette
+    /// member closest in hue, with black and white taken from the background
+    /// and foreground.
+    pub fn with_color_names(mut self, ansi: bool) -> Self {
+        const NAMES: [(&str, f32); 8] = [
+            ("black", 0.0), ("red", 0.0), ("green", 120.0), ("yellow", 60.0),
+            ("blue", 240.0), ("magenta", 300.0), ("cyan", 180.0), ("white", 0.0),
+        ];
+        if self.palette.is_empty() {
+            return self
+        }
+
+        let (darkest, lightest) = if self.background.luminance <= self.foreground.luminance {
+            (self.background, self.foreground)
+        } else {
+            (self.foreground, self.background)
+        };
+        for (i, (name, hue)) in NAMES.into_iter().enumerate() {
+            let c = match (ansi, name) {
+                (true, _) if i < self.palette.len() => self.palette[i],
+                (_, "black") => darkest,
+                (_, "white") => lightest,
+                _ => self.closest_to_hue(hue, 180.0).unwrap_or_else(|| self.color_for_hue(hue)),
+            };
+            self.named.push((name.to_string(), c));
+        }
+        return self
+    }
+
+    /// Color for base16's `base00`-`base0F`: 00-07 step from the background
+    /// to the foreground and past it, 08-0F are red, orange, yellow, green,
+    /// cyan, blue, magenta and brown.
+    pub fn base16(&self, index: usize) -> Option<Color> {
+        let bg = self.background;
+        let fg = self.foreground;
+        let named = |name: &str, hue: f32| {
+            self.named.iter().find(|(n, _)| n == name).map(|(_, c)| *c).unwrap_or_else(|| self.color_for_hue(hue))
+        };
+        let beyond_fg = |step: f32| {
+            let (l, c, h) = fg.lch();
+            let away = if bg.luminance <= fg.luminance { 1.0 } else { -1.0 };
+            Color::from_lch((l + away * step).clamp(0.0, 100.0), c, h)
+        };
+        let orange = self.color_for_hue(30.0);
+
+        let c = match index {
+            0x00 => bg,
+            0x01 => bg.mix(&fg, 0.12),
+            0x02 => bg.mix(&fg, 0.20),
+            0x03 => bg.mix(&fg, 0.40),
+            0x04 => bg.mix(&fg, 0.70),
+            0x05 => fg,
+            0x06 => beyond_fg(5.0),
+            0x07 => beyond_fg(10.0),
+            0x08 => named("red", 0.0),
+            0x09 => orange,
+            0x0A => named("yellow", 60.0),
+            0x0B => named("green", 120.0),
+            0x0C => named("cyan", 180.0),
+            0x0D => named("blue", 240.0),
+            0x0E => named("magenta", 300.0),
+            0x0F => {
+                let (l, c, h) = orange.lch();
+                Color::from_lch((l - 20.0).max(0.0), c * 0.7, h)
+            }
+            _ => return None,
+        };
+        return Some(c)
+    }
+
+    /// Adds layered background roles in the style of Catppuccin: `crust` and
+    /// `mantle` sit behind the background, `surface0-2`, `overlay0-2` and
+    /// `subtext0-1` step from it towards the foreground.
+    pub fn with_surfaces(mut self) -> Self {
+        const LAYERS: [(&str, f32); 8] = [
+            ("surface0", 0.12), ("surface1", 0.20), ("surface2", 0.28),
+            ("overlay0", 0.40), ("overlay1", 0.50), ("overlay2", 0.60),
+            ("subtext0", 0.70), ("subtext1", 0.80),
+        ];
+
+        let bg = self.background;
+        let fg = self.foreground;
+        for (name, t) in LAYERS {
+            self.named.push((name.to_string(), bg.mix(&fg, t)));
+        }
+
+        let (l, c, h) = bg.lch();
+        let away = if bg.luminance <= fg.luminance { -1.0 } else { 1.0 };
+        self.named.push(("mantle".to_string(), Color::from_lch((l + away * 3.0).clamp(0.0, 100.0), c, h)));
+        self.named.push(("crust".to_string(), Color::from_lch((l + away * 6.0).clamp(0.0, 100.0), c, h)));
+        return self
+    }
+
+    /// Adds `color<i>_l<k>` and `color<i>_d<k>` tints and shades for every
+    /// palette color, `steps` of each, 8 L* apart.
+    pub fn with_ramps(mut self, steps: u32) -> Self {
+        const STEP: f32 = 8.0;
+
+        for (i, c) in self.palette.iter().enumerate() {
+            let (l, chroma, hue) = c.lch();
+            for k in 1..=steps {
+                let offset = STEP * k as f32;
+                self.named.push((format!("color{}_l{}", i, k), Color::from_lch((l + offset).min(100.0), chroma, hue)));
+                self.named.push((format!("color{}_d{}", i, k), Color::from_lch((l - offset).max(0.0), chroma, hue)));
+            }
+        }
+        return self
+    }
+
+    /// Stretches palette lightness away from (or, below 1.0, towards) the background.
+    pub fn with_contrast(mut self, contrast: f32) -> Self {
+        if contrast == 1.0 { return self; }
+
+        let (bg_l, _c, _h) = self.background.lch();
+        for c in self.palette.iter_mut() {
+            let (l, chroma, hue) = c.lch();
+            *c = Color::from_lch((bg_l + (l - bg_l) * contrast).clamp(0.0, 100.0), chroma, hue);
+        }
+        return self
+    }
+
+    /// Pushes apart the lightness of palette pairs that stay distinct in full
+    /// color but become hard to tell apart with the given deficiency. The
+    /// red/green ANSI pairs are fixed first since they carry the most meaning.
+    pub fn with_cvd_safety(mut self, cvd: Cvd) -> Self {
+        const MIN_DELTA_E: f32 = 12.0;
+        const STEP:        f32 = 4.0;
+
+        let n = self.palette.len();
+        let mut pairs: Vec<(usize, usize)> = vec![(1, 2), (9, 10)];
+        for i in 0..n {
+            for j in i + 1..n {
+                if !pairs.contains(&(i, j)) { pairs.push((i, j)); }
+            }
+        }
+
+        for (i, j) in pairs {
+            if j >= n { continue; }
+            if self.palette[i].delta_e(&self.palette[j]) < MIN_DELTA_E { continue; }
+
+            for _ in 0..12 {
+                let a = self.palette[i].simulate_cvd(cvd);
+                let b = self.palette[j].simulate_cvd(cvd);
+                if a.delta_e(&b) >= MIN_DELTA_E { break; }
+
+                let (li, _ci, _hi) = self.palette[i].lch();
+                let (lj, cj, hj) = self.palette[j].lch();
+                let up = if lj >= 100.0 - STEP { false } else if lj <= STEP { true } else { lj >= li };
+                let l = if up { lj + STEP } else { lj - STEP };
+                self.palette[j] = Color::from_lch(l.clamp(0.0, 100.0), cj, hj);
+            }
+        }
+        return self
+    }
+
+    /// Guarantees the foreground, every palette color and every named color
+    /// reach `min_ratio` contrast against the background, except the layers
+    /// meant to sit behind text (`crust`, `mantle`, `surface0-2`). Roles
+    /// derived afterwards aren't covered, so this runs last.
+    pub fn with_min_contrast(mut self, min_ratio: f32) -> Self {
+        const BACKGROUND_LAYERS: [&str; 5] = ["crust", "mantle", "surface0", "surface1", "surface2"];
+
+        let bg = self.background;
+        self.foreground = self.foreground.with_min_contrast(&bg, min_ratio);
+        self.palette = self.palette.into_iter().map(|c| c.with_min_contrast(&bg, min_ratio)).collect();
+        self.named = self.named
+                .into_iter()
+                .map(|(name, c)| match BACKGROUND_LAYERS.contains(&name.as_str()) {
+                    true  => (name, c),
+                    false => (name, c.with_min_contrast(&bg, min_ratio)),
+                })
+                .collect();
+        return self
+    }
+
+    /// Replaces colors 8-15 with lightened and slightly saturated variants of colors 0-7.
+    pub fn with_derived_brights(mut self) -> Self {
+        let normal = self.palette.len().min(8);
+        self.palette.truncate(normal);
+
+        for i in 0..normal {
+            let bright = self.palette[i].brightened();
+            self.palette.push(bright);
+        }
+        return self
+    }
+
+    /// Adds `accent` and `accent2`: the most saturated palette colors that also
+    /// cover a meaningful share of the image, with the second one in a different hue.
+    pub fn with_accents(mut self, samples: &[Color]) -> Self {
+        const MIN_SHARE: f32 = 0.03;
+        const MIN_HUE_DISTANCE: f32 = 30.0;
+
+        if self.palette.is_empty() { return self; }
+
+        let mut counts = vec![0usize; self.palette.len()];
+        for sample in samples {
+            let nearest = self.palette
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| sample.distance_to(a.1).total_cmp(&sample.distance_to(b.1)))
+                    .map_or(0, |(i, _)| i);
+            counts[nearest] += 1;
+        }
+
+        let min_count = (samples.len() as f32 * MIN_SHARE) as usize;
+        let mut candidates: Vec<Color> = self.palette
+                .iter()
+                .zip(&counts)
+                .filter(|&(_, &n)| n >= min_count)
+                .map(|(c, _)| *c)
+                .collect();
+        if candidates.is_empty() {
+            candidates = self.palette.clone();
+        }
+        candidates.sort_unstable_by(|a, b| b.hsl().1.total_cmp(&a.hsl().1).then(b.chroma.cmp(&a.chroma)));
+
+        let accent = candidates[0];
+        let accent2 = candidates
+                .iter()
+                .find(|c| hue_distance(c.hsl().0, accent.hsl().0) >= MIN_HUE_DISTANCE)
+                .or(candidates.get(1))
+                .copied()
+                .unwrap_or(accent);
+
+        self.named.push(("accent".to_string(), accent));
+        self.named.push(("accent2".to_string(), accent2));
+        return self
+    }
+
+    pub fn with_theme(self, light: bool) -> Self {
+        if !light { return self; }
+
+        Self {
+            palette: self.palette,
+            background: self.foreground,
+            foreground: self.background,
+            named: self.named,
+            monochrome: self.monochrome,
         }
     }
 }
 
+pub fn is_light(samples: &[Color]) -> bool {
+    if samples.is_empty() { return false }
+
+    let sum: f32 = samples.iter().map(|c| c.luminance).sum();
+    return sum / samples.len() as f32 > 0.5
+}
+
+/// True when no sample carries real color, e.g. black and white photos.
+pub fn is_monochrome(samples: &[Color]) -> bool {
+    const MONOCHROME_CHROMA: u8 = 24;
+    return samples.iter().all(|c| c.chroma < MONOCHROME_CHROMA)
+}
+
+/// Neutral background and foreground from the image with a synthesized, softly
+/// colored ANSI style palette, instead of 16 nearly identical greys.
+pub fn monochrome_generate_colorscheme(samples: &[Color]) -> Colorscheme {
+    const HUES: [f32; 6] = [0.0, 120.0, 60.0, 240.0, 300.0, 180.0];
+
+    let (darkest, lightest) = darkest_and_lightest(samples);
+    let (_h, _s, dark_l) = darkest.hsl();
+    let (_h, _s, light_l) = lightest.hsl();
+
+    let mut palette: Vec<Color> = Vec::with_capacity(16);
+    for (bright, l) in [(false, 0.5), (true, 0.65)] {
+        palette.push(Color::from_hsl(0.0, 0.0, if bright { (dark_l + light_l) / 2.0 } else { dark_l + 0.1 }));
+        for hue in HUES {
+            palette.push(Color::from_hsl(hue, 0.35, l));
+        }
+        palette.push(Color::from_hsl(0.0, 0.0, if bright { light_l } else { light_l - 0.1 }));
+    }
+
+    return Colorscheme { palette, 
+                         background: darkest, 
+                         foreground: lightest,
+                         named: Vec::new(),
+                         monochrome: true }
+}
+
+/// Decides which samples may become palette candidates; background and
+/// foreground detection always sees every sample.
+#[derive(Debug, Default)]
+pub struct SampleFilter {
+    pub min_chroma: u8,
+    pub exclude_hues: Vec<(f32, f32)>,
+    pub only_hues: Vec<(f32, f32)>,
+}
+
+impl SampleFilter {
+    pub fn accepts(&self, c: &Color) -> bool {
+        // Below this chroma the hue is mostly noise, so hue ranges don't apply
+        const HUELESS_CHROMA: u8 = 16;
+
+        if c.chroma < self.min_chroma { return false }
+        if c.chroma < HUELESS_CHROMA { return true }
+
+        let (hue, _s, _l) = c.hsl();
+        let in_range = |&(from, to): &(f32, f32)| {
+            if from <= to { hue >= from && hue <= to } else { hue >= from || hue <= to }
+        };
+
+        if self.exclude_hues.iter().any(in_range) { return false }
+        return self.only_hues.is_empty() || self.only_hues.iter().any(in_range)
+    }
+}
+
+/// Averaged 4x4 samples on a 32x32 grid.
+pub fn sample_grid(img: &DynamicImage) -> Vec<Color> {
+    const DIVISOR:      usize = 32;
+    const SAMPLE_COUNT: usize = 1024;
+
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+    let step_x = (w / DIVISOR).max(1);
+    let step_y = (h / DIVISOR).max(1);
+    let mut samples: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
+
+    'pixels: for y in (0..h).step_by(step_y) {
+        for x in (0..w).step_by(step_x) {
+            if samples.len() >= SAMPLE_COUNT {
+                break 'pixels;
+            }
+            if let Some(c) = sample_4by4_area(img, x, y, w, h) {
+                samples.push(c);
+            }
+        }
+    }
+    return samples
+}
+
+/// Single pixels on a 32x32 grid.
+pub fn sample_grid_pixels(img: &DynamicImage) -> Vec<Color> {
+    const DIVISOR:      usize = 32;
+    const SAMPLE_COUNT: usize = 1024;
+
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+    let step_x = (w / DIVISOR).max(1);
+    let step_y = (h / DIVISOR).max(1);
+    let mut samples: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
+
+    'pixels: for y in (0..h).step_by(step_y) {
+        for x in (0..w).step_by(step_x) {
+            if samples.len() >= SAMPLE_COUNT {
+                break 'pixels;
+            }
+            samples.push(Color::from_rgba(img.get_pixel(x as u32, y as u32)));
+        }
+    }
+    return samples
+}
+
+/// Every pixel of the image after downscaling it to fit in 256x256.
+pub fn sample_full(img: &DynamicImage) -> Vec<Color> {
+    const MAX_SIZE: u32 = 256;
+
+    let small = if img.width() > MAX_SIZE || img.height() > MAX_SIZE {
+        img.resize(MAX_SIZE, MAX_SIZE, FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+
+    return small.pixels()
+            .filter(|(_, _, p)| p.0[3] != 0)
+            .map(|(_, _, p)| Color::from_rgba(p))
+            .collect()
+}
+
+/// Pools samples from several images so each image contributes in
+/// proportion to its weight, regardless of how many samples it produced.
+pub fn pool_weighted_samples(sets: &[(Vec<Color>, f32)]) -> Vec<Color> {
+    let largest = sets.iter().map(|(samples, _)| samples.len()).max().unwrap_or(0);
+    let heaviest = sets.iter().map(|&(_, weight)| weight).fold(0.0, f32::max);
+    if sets.len() == 1 || largest == 0 {
+        return sets.iter().flat_map(|(samples, _)| samples.clone()).collect()
+    }
+
+    return sets.iter()
+            .filter(|(samples, _)| !samples.is_empty())
+            .flat_map(|(samples, weight)| {
+                let count = (largest as f32 * weight / heaviest).round().max(1.0) as usize;
+                (0..count).map(move |i| samples[i * samples.len() / count])
+            })
+            .collect()
+}
+
+/// Darkest and lightest samples, ignoring near black and near white.
+fn darkest_and_lightest(samples: &[Color]) -> (Color, Color) {
+    let mut darkest  = Color {r: 255, g: 255, b: 255, chroma: 0, luminance: 1.0};
+    let mut lightest = Color {r: 0, g: 0, b: 0, chroma: 0, luminance: 0.0};
+
+    for &c in samples {
+        if c.luminance < darkest.luminance && c.luminance > 0.05  { darkest = c };
+        if c.luminance > lightest.luminance && c.luminance < 0.95 { lightest = c };
+    }
+    return (darkest, lightest)
+}
+
 pub fn sample_4by4_area(img: &DynamicImage, x: usize, y: usize, w: usize, h: usize) -> Option<Color> {
     if x + 3 >= w || y + 3 >= h { return None }
 
@@ -100,102 +926,80 @@ pub fn sample_4by4_area(img: &DynamicImage, x: usize, y: usize, w: usize, h: usi
     ])))
 }
 
-pub fn aaverage_generate_colorscheme(img: &DynamicImage) -> Colorscheme {
-    const DIVISOR:       usize = 32;
-    const SAMPLE_COUNT:  usize = 1024;
+/// `distinctness` is the starting Manhattan distance two palette colors must be
+/// apart; it is relaxed step by step until the palette is full.
+pub fn aaverage_generate_colorscheme(samples: &[Color], filter: &SampleFilter, distinctness: u32) -> Colorscheme {
     const PALETTE_COUNT: usize = 16;
     
-    let w = img.width() as usize;
-    let h = img.height() as usize;
-    let mut samples: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
-    
-    let step_x = (w / DIVISOR).max(1);
-    let step_y = (h / DIVISOR).max(1);
-    let mut darkest  = Color {r: 255, g: 255, b: 255, chroma: 0, luminance: 1.0};
-    let mut lightest = Color {r: 0, g: 0, b: 0, chroma: 0, luminance: 0.0};
+    let (darkest, lightest) = darkest_and_lightest(samples);
+    let mut samples: Vec<Color> = samples
+            .iter()
+            .filter(|c| filter.accepts(c))
+            .copied()
+            .collect();
+    samples.sort_unstable_by_key(|c| Reverse(c.chroma));
     
-    'pixels: for y in (0..h).step_by(step_y) {
-        for x in (0..w).step_by(step_x) {
-            if samples.len() >= SAMPLE_COUNT {
-                break 'pixels;
-            }
-           
-            let Some(c) = sample_4by4_area(img, x, y, w, h) else {
+    let mut palette: Vec<Color> = Vec::with_capacity(PALETTE_COUNT);
+    let mut threshold = distinctness as i32;
+    'relax: loop {
+        for sample in &samples {
+            let diff_bg = (sample.luminance - darkest.luminance).abs(); 
+            let diff_fg = (sample.luminance - lightest.luminance).abs(); 
+            if diff_bg < 0.08 || diff_fg < 0.08 {
                 continue;
-            };
-            if c.luminance < darkest.luminance && c.luminance > 0.05  { darkest = c };
-            if c.luminance > lightest.luminance && c.luminance < 0.95 { lightest = c };
-
-            samples.push(c);
-        }
-    }
-    samples.sort_unstable_by(|a, b| b.chroma.cmp(&a.chroma));
-    
-    let mut palette: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
-    for sample in &samples {
-        let diff_bg = (sample.luminance - darkest.luminance).abs(); 
-        let diff_fg = (sample.luminance - lightest.luminance).abs(); 
-        if diff_bg < 0.08 || diff_fg < 0.08 {
-            continue;
-        }
+            }
 
-        let mut distinct: bool = true;
-        for &existing in &palette {
-            let manh_d = (sample.r as i32 - existing.r as i32).abs()
-                       + (sample.g as i32 - existing.g as i32).abs()
-                       + (sample.b as i32 - existing.b as i32).abs();
-            if manh_d < 50 {
-                distinct = false;
-                break;
+            let mut distinct: bool = true;
+            for &existing in &palette {
+                let manh_d = (sample.r as i32 - existing.r as i32).abs()
+                           + (sample.g as i32 - existing.g as i32).abs()
+                           + (sample.b as i32 - existing.b as i32).abs();
+                if manh_d < threshold.max(1) {
+                    distinct = false;
+                    break;
+                }
             }
-        }
 
-        if distinct {
-            let c = sample;
-            palette.push(*c);
-            if palette.len() >= PALETTE_COUNT {
-                break;
+            if distinct {
+                let c = sample;
+                palette.push(*c);
+                if palette.len() >= PALETTE_COUNT {
+                    break 'relax;
+                }
             }
         }
+
+        if threshold == 0 { break; }
+        threshold = threshold * 4 / 5;
     }
-    palette.sort_unstable_by(|a, b| b.chroma.cmp(&a.chroma));
+    palette.sort_unstable_by_key(|c| Reverse(c.chroma));
     return Colorscheme { palette: palette, 
                          background: darkest, 
-                         foreground: lightest }
+                         foreground: lightest,
+                         named: Vec::new(),
+                         monochrome: false }
 }
 
-pub fn kmeans_generate_colorscheme(img: &DynamicImage) -> Colorscheme {
-    const DIVISOR:       usize = 32;
-    const SAMPLE_COUNT:  usize = 1024;
+pub fn kmeans_generate_colorscheme(samples: &[Color], filter: &SampleFilter) -> Colorscheme {
     const PALETTE_COUNT: usize = 16;
     
-    let w = img.width() as usize;
-    let h = img.height() as usize;
-    let mut samples: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
-    
-    let step_x = (w / DIVISOR).max(1);
-    let step_y = (h / DIVISOR).max(1);
-    let mut darkest  = Color {r: 255, g: 255, b: 255, chroma: 0, luminance: 1.0};
-    let mut lightest = Color {r: 0, g: 0, b: 0, chroma: 0, luminance: 0.0};
-    
-    'pixels: for y in (0..h).step_by(step_y) {
-        for x in (0..w).step_by(step_x) {
-            if samples.len() >= SAMPLE_COUNT {
-                break 'pixels;
-            }
-            let pixel = img.get_pixel(x as u32, y as u32);
-            let c = Color::from_rgba(pixel);
-            
-            if c.luminance < darkest.luminance && c.luminance > 0.05  { darkest = c };
-            if c.luminance > lightest.luminance && c.luminance < 0.95 { lightest = c };
-
-            samples.push(c);
-        }
+    let (darkest, lightest) = darkest_and_lightest(samples);
+    let mut samples: Vec<Color> = samples
+            .iter()
+            .filter(|c| filter.accepts(c))
+            .copied()
+            .collect();
+    samples.sort_unstable_by_key(|c| Reverse(c.chroma));
+    if samples.is_empty() {
+        return Colorscheme { palette: Vec::new(), 
+                             background: darkest, 
+                             foreground: lightest,
+                             named: Vec::new(),
+                             monochrome: false }
     }
-    samples.sort_unstable_by(|a, b| b.chroma.cmp(&a.chroma));
     
     let mut centers: Vec<Color> = (0..PALETTE_COUNT)
-            .map(|i| samples[i * (SAMPLE_COUNT / PALETTE_COUNT)])
+            .map(|i| samples[i * samples.len() / PALETTE_COUNT])
             .collect();
     for _iter in 0..10 {
         let mut r_sum  = [0i32; PALETTE_COUNT];
@@ -242,56 +1046,60 @@ pub fn kmeans_generate_colorscheme(img: &DynamicImage) -> Colorscheme {
             }
         }
     }
-    centers.sort_unstable_by(|a, b| b.chroma.cmp(&a.chroma));
+    centers.sort_unstable_by_key(|c| Reverse(c.chroma));
     return Colorscheme { palette: centers, 
                          background: darkest, 
-                         foreground: lightest }
+                         foreground: lightest,
+                         named: Vec::new(),
+                         monochrome: false }
 }
 
-pub fn ansi_generate_colorscheme(img: &DynamicImage) -> Colorscheme {
-    const DIVISOR:       usize = 32;
-    const SAMPLE_COUNT:  usize = 1024;
-
+pub fn ansi_generate_colorscheme(samples: &[Color], filter: &SampleFilter) -> Colorscheme {
     const ANSI_BASE: [(u8, u8, u8); 16] = [
         (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
         (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
         (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
         (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
     ];
+    const ANSI_HUE: [Option<f32>; 16] = [
+        None, Some(0.0), Some(120.0), Some(60.0), Some(240.0), Some(300.0), Some(180.0), None,
+        None, Some(0.0), Some(120.0), Some(60.0), Some(240.0), Some(300.0), Some(180.0), None,
+    ];
+    const HUE_WINDOW: f32 = 30.0;
+    const MIN_CHROMA: u8  = 24;
 
-    let w = img.width() as usize;
-    let h = img.height() as usize;
-    let mut samples: Vec<Color> = Vec::with_capacity(SAMPLE_COUNT);
-    
-    let step_x = (w / DIVISOR).max(1);
-    let step_y = (h / DIVISOR).max(1);
-    let mut darkest  = Color {r: 255, g: 255, b: 255, chroma: 0, luminance: 1.0};
-    let mut lightest = Color {r: 0, g: 0, b: 0, chroma: 0, luminance: 0.0};
-    
-    'pixels: for y in (0..h).step_by(step_y) {
-        for x in (0..w).step_by(step_x) {
-            if samples.len() >= SAMPLE_COUNT {
-                break 'pixels;
-            }
-           
-            let Some(c) = sample_4by4_area(img, x, y, w, h) else {
-                continue;
-            };
-            if c.luminance < darkest.luminance && c.luminance > 0.05  { darkest = c };
-            if c.luminance > lightest.luminance && c.luminance < 0.95 { lightest = c };
-
-            samples.push(c);
-        }
-    }
+    let (darkest, lightest) = darkest_and_lightest(samples);
+    let samples: Vec<Color> = samples
+            .iter()
+            .filter(|c| filter.accepts(c))
+            .copied()
+            .collect();
 
     let mut palette = Vec::with_capacity(16);
     
-    for &(base_r, base_g, base_b) in &ANSI_BASE {
+    for (i, &(base_r, base_g, base_b)) in ANSI_BASE.iter().enumerate() {
         let base = Color::from_rgba(Rgba([base_r, base_g, base_b, 255]));
-        let mut best_sample = &samples[0];
+
+        // Chromatic slots only take samples from their own hue family, so an
+        // orange sample can never end up as green just by raw RGB distance
+        let candidates: Vec<&Color> = match ANSI_HUE[i] {
+            Some(hue) => samples
+                    .iter()
+                    .filter(|s| s.chroma >= MIN_CHROMA && hue_distance(s.hsl().0, hue) <= HUE_WINDOW)
+                    .collect(),
+            None => samples.iter().collect(),
+        };
+
+        if candidates.is_empty() {
+            let (hue, sat) = ANSI_HUE[i].map_or((0.0, 0.0), |hue| (hue, 0.6));
+            palette.push(Color::from_hsl(hue, sat, if i < 8 { 0.45 } else { 0.6 }));
+            continue;
+        }
+
+        let mut best_sample = candidates[0];
         let mut best_dist = f32::MAX; 
 
-        for sample in &samples {
+        for sample in candidates {
             let dist = sample.distance_to(&base);
             if dist < best_dist {
                 best_dist = dist;
@@ -306,5 +1114,190 @@ pub fn ansi_generate_colorscheme(img: &DynamicImage) -> Colorscheme {
 
     return Colorscheme { palette, 
                          background: darkest, 
-                         foreground: lightest }
+                         foreground: lightest,
+                         named: Vec::new(),
+                         monochrome: false }
+}
+
+pub fn material_generate_colorscheme(samples: &[Color], filter: &SampleFilter) -> Colorscheme {
+    const HUE_BINS: usize = 36;
+    const TONES: [u8; 13] = [0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 95, 99, 100];
+
+    // Score hue bins by accumulated chroma, so a large colorful area wins over
+    // a small very saturated one and grey pixels barely count
+    let mut scores = [0f32; HUE_BINS];
+    let mut chroma_max = [0f32; HUE_BINS];
+    for c in samples.iter().filter(|c| filter.accepts(c)) {
+        let (_l, chroma, hue) = c.lch();
+        if chroma < 5.0 { continue; }
+
+        let bin = (hue / 360.0 * HUE_BINS as f32) as usize % HUE_BINS;
+        scores[bin] += chroma;
+        chroma_max[bin] = chroma_max[bin].max(chroma);
+    }
+
+    let best = (0..HUE_BINS)
+            .max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+            .unwrap_or(0);
+    let seed_hue = (best as f32 + 0.5) * 360.0 / HUE_BINS as f32;
+    let seed_chroma = chroma_max[best];
+
+    let ramps: [(&str, f32, f32); 6] = [
+        ("primary",         seed_hue,                          seed_chroma.max(48.0)),
+        ("secondary",       seed_hue,                          16.0),
+        ("tertiary",        (seed_hue + 60.0).rem_euclid(360.0), 24.0),
+        ("surface",         seed_hue,                          4.0),
+        ("surface_variant", seed_hue,                          8.0),
+        ("error",           25.0,                              84.0),
+    ];
+
+    let mut named: Vec<(String, Color)> = Vec::with_capacity(ramps.len() * TONES.len());
+    for (name, hue, chroma) in ramps {
+        for tone in TONES {
+            named.push((format!("{}_{}", name, tone), Color::from_lch(tone as f32, chroma, hue)));
+        }
+    }
+
+    let tone_of = |name: &str, tone: u8| {
+        let key = format!("{}_{}", name, tone);
+        named.iter().find(|(n, _)| *n == key).map(|(_, c)| *c).unwrap_or_default()
+    };
+    let mut palette: Vec<Color> = Vec::with_capacity(16);
+    for tone in [40, 60, 80, 90] {
+        for name in ["primary", "secondary", "tertiary", "error"] {
+            palette.push(tone_of(name, tone));
+        }
+    }
+
+    let background = tone_of("surface", 10);
+    let foreground = tone_of("surface", 90);
+    return Colorscheme { palette, 
+                         background, 
+                         foreground,
+                         named,
+                         monochrome: false }
+}
+
+pub fn vibrant_generate_colorscheme(samples: &[Color], filter: &SampleFilter) -> Colorscheme {
+    const PALETTE_COUNT: usize = 16;
+
+    // (name, min lightness, target lightness, max lightness, min saturation, target saturation, max saturation)
+    const SWATCHES: [(&str, f32, f32, f32, f32, f32, f32); 6] = [
+        ("vibrant",       0.30, 0.50, 0.70, 0.35, 1.0, 1.0),
+        ("light_vibrant", 0.55, 0.74, 1.00, 0.35, 1.0, 1.0),
+        ("dark_vibrant",  0.00, 0.26, 0.45, 0.35, 1.0, 1.0),
+        ("muted",         0.30, 0.50, 0.70, 0.00, 0.3, 0.4),
+        ("light_muted",   0.55, 0.74, 1.00, 0.00, 0.3, 0.4),
+        ("dark_muted",    0.00, 0.26, 0.45, 0.00, 0.3, 0.4),
+    ];
+
+    // Quantize to 4 bits per channel, keeping the population and channel sums of each box
+    let mut boxes: Vec<(u32, u32, u32, u32)> = vec![(0, 0, 0, 0); 4096];
+    for c in samples.iter().filter(|c| filter.accepts(c)) {
+        let idx = ((c.r as usize >> 4) << 8) | ((c.g as usize >> 4) << 4) | (c.b as usize >> 4);
+        boxes[idx].0 += 1;
+        boxes[idx].1 += c.r as u32;
+        boxes[idx].2 += c.g as u32;
+        boxes[idx].3 += c.b as u32;
+    }
+
+    let mut swatches: Vec<(Color, u32)> = boxes
+            .iter()
+            .filter(|b| b.0 > 0)
+            .map(|&(n, r, g, b)| (Color::from_rgba(Rgba([(r / n) as u8, (g / n) as u8, (b / n) as u8, 255])), n))
+            .collect();
+    swatches.sort_unstable_by_key(|s| Reverse(s.1));
+    let max_population = swatches.first().map_or(1, |s| s.1) as f32;
+
+    let mut named: Vec<(String, Color)> = Vec::with_capacity(SWATCHES.len());
+    for (name, min_l, target_l, max_l, min_s, target_s, max_s) in SWATCHES {
+        let mut best: Option<(Color, f32)> = None;
+        for &(c, population) in &swatches {
+            let (_h, s, l) = c.hsl();
+            if s < min_s || s > max_s || l < min_l || l > max_l { continue; }
+            if named.iter().any(|(_, used)| used.distance_to(&c) == 0.0) { continue; }
+
+            let score = (1.0 - (s - target_s).abs()) * 3.0
+                      + (1.0 - (l - target_l).abs()) * 6.5
+                      + population as f32 / max_population * 0.5;
+            if best.is_none_or(|(_, b)| score > b) {
+                best = Some((c, score));
+            }
+        }
+        if let Some((c, _)) = best {
+            named.push((name.to_string(), c));
+        }
+    }
+
+    // Fill in swatches the image had no candidate for from their vibrant/muted base
+    for (name, _min_l, target_l, ..) in SWATCHES {
+        if named.iter().any(|(n, _)| n == name) { continue; }
+
+        let base = if name.ends_with("vibrant") { "vibrant" } else { "muted" };
+        let Some(&(_, base)) = named.iter().find(|(n, _)| n == base) else {
+            continue;
+        };
+        let (hue, sat, _l) = base.hsl();
+        named.push((name.to_string(), Color::from_hsl(hue, sat, target_l)));
+    }
+
+    let swatch = |name: &str| named.iter().find(|(n, _)| n == name).map(|(_, c)| *c);
+    let background = swatch("dark_muted")
+            .or_else(|| swatches.iter().map(|s| s.0).min_by(|a, b| a.luminance.total_cmp(&b.luminance)))
+            .unwrap_or_default();
+    let foreground = swatch("light_muted")
+            .or_else(|| swatches.iter().map(|s| s.0).max_by(|a, b| a.luminance.total_cmp(&b.luminance)))
+            .unwrap_or_default();
+
+    let mut palette: Vec<Color> = swatches.iter().take(PALETTE_COUNT).map(|s| s.0).collect();
+    palette.sort_unstable_by_key(|c| Reverse(c.chroma));
+
+    return Colorscheme { palette, 
+                         background, 
+                         foreground,
+                         named,
+                         monochrome: false }
+}
+
+/// Builds a full scheme around one color: background and foreground are
+/// near-neutral tints of its hue, the ANSI hues lean a little towards it and
+/// take its chroma, and the slot closest in hue becomes the seed itself.
+pub fn seed_generate_colorscheme(seed: Color) -> Colorscheme {
+    // OKLCh hues of red, green, yellow, blue, magenta and cyan
+    const HUES: [f32; 6] = [25.0, 145.0, 90.0, 260.0, 325.0, 200.0];
+    const MAX_PULL: f32 = 10.0;
+    const ACCENT2_TURN: f32 = 60.0;
+
+    let (seed_l, seed_c, seed_h) = seed.oklch();
+    let chroma = seed_c.clamp(0.08, 0.18);
+    let tint = (seed_c * 0.2).min(0.03);
+
+    let seed_slot = HUES.iter()
+            .enumerate()
+            .min_by(|a, b| hue_distance(*a.1, seed_h).total_cmp(&hue_distance(*b.1, seed_h)))
+            .map_or(0, |(i, _)| i);
+
+    let mut palette: Vec<Color> = Vec::with_capacity(16);
+    for (bright, l) in [(false, 0.68), (true, 0.78)] {
+        palette.push(Color::from_oklch(if bright { 0.45 } else { 0.28 }, tint, seed_h));
+        for (i, hue) in HUES.into_iter().enumerate() {
+            if i == seed_slot && !bright {
+                palette.push(seed);
+                continue;
+            }
+            let offset = (seed_h - hue + 540.0).rem_euclid(360.0) - 180.0;
+            palette.push(Color::from_oklch(l, chroma, (hue + (offset * 0.15).clamp(-MAX_PULL, MAX_PULL)).rem_euclid(360.0)));
+        }
+        palette.push(Color::from_oklch(if bright { 0.95 } else { 0.82 }, tint, seed_h));
+    }
+
+    // The seed itself is the accent, the second one an analogous hue
+    let accent2 = Color::from_oklch(seed_l, seed_c, (seed_h + ACCENT2_TURN).rem_euclid(360.0));
+    let named = vec![("accent".to_string(), seed), ("accent2".to_string(), accent2)];
+
+    return Colorscheme { palette, 
+                         background: Color::from_oklch(0.2, tint, seed_h), 
+                         foreground: Color::from_oklch(0.9, tint, seed_h),
+                         named,
+                         monochrome: false }
 }
@@ -0,0 +1,1372 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Export;
+use crate::colorscheme::{Color, Colorscheme};
+
+/// A built-in output, written to the cache dir with `--export` alongside the
+/// user's templates. Like them, it can be mapped to a destination in
+/// `targets.toml` by its file name.
+pub trait Exporter {
+    /// File name, relative to the cache dir.
+    fn default_path(&self) -> &'static str;
+    /// File content, bytes so binary formats fit as well. `variables` are the
+    /// same text variables templates see, e.g. `wallpaper` and `method`.
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8>;
+    /// Runs after the file at `path` changed, for programs that need telling.
+    /// `destination` is where `targets.toml` maps it, if anywhere.
+    fn reload(&self, _path: &Path, _destination: Option<&Path>) {}
+}
+
+/// `relative` under the home directory.
+fn home_path(relative: &str) -> Option<PathBuf> {
+    return env::var("HOME").ok().map(|home| Path::new(&home).join(relative))
+}
+
+fn hex(c: &Color) -> String {
+    return format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+/// Escape sequences that recolor a running terminal, `cat` them to apply.
+struct Sequences;
+
+impl Exporter for Sequences {
+    fn default_path(&self) -> &'static str {
+        "sequences"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let osc = |code: &str, c: &Color| format!("\x1b]{};{}\x1b\\", code, hex(c));
+        let mut out = String::new();
+        for (i, c) in colorscheme.palette.iter().enumerate() {
+            out.push_str(&osc(&format!("4;{}", i), c));
+        }
+        out.push_str(&osc("10", &colorscheme.foreground));
+        out.push_str(&osc("11", &colorscheme.background));
+        out.push_str(&osc("12", &colorscheme.foreground));
+        // urxvt's border color
+        out.push_str(&osc("708", &colorscheme.background));
+        return out.into_bytes()
+    }
+}
+
+fn variable<'a>(variables: &'a [(String, String)], name: &str) -> &'a str {
+    return variables.iter().find(|(n, _)| n == name).map_or("", |(_, v)| v.as_str())
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"'  => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped
+}
+
+type Fields = (Vec<(&'static str, String)>, Vec<(&'static str, Vec<(String, String)>)>);
+
+/// What the JSON and YAML exports hold, laid out like pywal's `colors.json`
+/// so tools reading that can read these: text fields, then groups of colors
+/// with the named colors added under `named`.
+fn scheme_fields(colorscheme: &Colorscheme, variables: &[(String, String)]) -> Fields {
+    let mut fields: Vec<(&'static str, String)> = ["wallpaper", "method", "saturation"]
+            .into_iter()
+            .map(|key| (key, variable(variables, key).to_string()))
+            .collect();
+    fields.push(("alpha", "100".to_string()));
+
+    let special = vec![
+        ("background".to_string(), hex(&colorscheme.background)),
+        ("foreground".to_string(), hex(&colorscheme.foreground)),
+        ("cursor".to_string(),     hex(&colorscheme.foreground)),
+    ];
+    let colors = colorscheme.palette.iter().enumerate().map(|(i, c)| (format!("color{}", i), hex(c))).collect();
+    let named = colorscheme.named.iter().map(|(n, c)| (n.clone(), hex(c))).collect();
+    return (fields, vec![("special", special), ("colors", colors), ("named", named)])
+}
+
+/// `colors.json`
+struct Json;
+
+impl Exporter for Json {
+    fn default_path(&self) -> &'static str {
+        "colors.json"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let (fields, groups) = scheme_fields(colorscheme, variables);
+        let mut entries: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("    \"{}\": \"{}\"", key, json_escape(value)))
+                .collect();
+        for (group, colors) in groups {
+            let colors: Vec<String> = colors
+                    .iter()
+                    .map(|(name, c)| format!("        \"{}\": \"{}\"", json_escape(name), c))
+                    .collect();
+            entries.push(format!("    \"{}\": {{\n{}\n    }}", group, colors.join(",\n")));
+        }
+        return format!("{{\n{}\n}}\n", entries.join(",\n")).into_bytes()
+    }
+}
+
+/// `colors.yaml`, the same fields as `colors.json`.
+struct Yaml;
+
+impl Exporter for Yaml {
+    fn default_path(&self) -> &'static str {
+        "colors.yaml"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let (fields, groups) = scheme_fields(colorscheme, variables);
+        let mut out = String::new();
+        // Double quoted YAML strings escape the same way JSON ones do
+        for (key, value) in fields {
+            out.push_str(&format!("{}: \"{}\"\n", key, json_escape(&value)));
+        }
+        for (group, colors) in groups {
+            if colors.is_empty() {
+                out.push_str(&format!("{}: {{}}\n", group));
+                continue;
+            }
+            out.push_str(&format!("{}:\n", group));
+            for (name, c) in colors {
+                out.push_str(&format!("  {}: \"{}\"\n", name, c));
+            }
+        }
+        return out.into_bytes()
+    }
+}
+
+/// Every color by name: background, foreground, color0-15, then the named ones.
+fn all_colors(colorscheme: &Colorscheme) -> Vec<(String, Color)> {
+    let mut colors = vec![
+        ("background".to_string(), colorscheme.background),
+        ("foreground".to_string(), colorscheme.foreground),
+    ];
+    colors.extend(colorscheme.palette.iter().enumerate().map(|(i, c)| (format!("color{}", i), *c)));
+    colors.extend(colorscheme.named.iter().cloned());
+    return colors
+}
+
+/// `colors.css` with a custom property per color.
+struct Css;
+
+impl Exporter for Css {
+    fn default_path(&self) -> &'static str {
+        "colors.css"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = String::from(":root {\n");
+        for (name, c) in all_colors(colorscheme) {
+            out.push_str(&format!("    --{}: {};\n", identifier(&name), hex(&c)));
+        }
+        out.push_str("}\n");
+        return out.into_bytes()
+    }
+}
+
+/// `colors.scss` with a variable per color.
+struct Scss;
+
+impl Exporter for Scss {
+    fn default_path(&self) -> &'static str {
+        "colors.scss"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = String::new();
+        for (name, c) in all_colors(colorscheme) {
+            out.push_str(&format!("${}: {};\n", identifier(&name), hex(&c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `colors.Xresources` for urxvt, xterm and other X programs.
+struct Xresources;
+
+impl Exporter for Xresources {
+    fn default_path(&self) -> &'static str {
+        "colors.Xresources"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = String::new();
+        out.push_str(&format!("*.background: {}\n", hex(&colorscheme.background)));
+        out.push_str(&format!("*.foreground: {}\n", hex(&colorscheme.foreground)));
+        out.push_str(&format!("*.cursorColor: {}\n", hex(&colorscheme.foreground)));
+        for (i, c) in colorscheme.palette.iter().enumerate() {
+            out.push_str(&format!("*.color{}: {}\n", i, hex(c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// A color name usable as a shell, fish or SCSS variable or a CSS custom
+/// property: named colors from palette files can hold anything, which becomes
+/// `_`, and a leading digit gets one in front.
+fn identifier(name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("_{}", name)
+    }
+    return name
+}
+
+/// Quotes a value for POSIX shells, where `'` can't be escaped inside
+/// single quotes.
+fn shell_quote(value: &str) -> String {
+    return format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// `colors.sh` exporting a variable per color, to be sourced by scripts.
+struct Shell;
+
+impl Exporter for Shell {
+    fn default_path(&self) -> &'static str {
+        "colors.sh"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = format!("export WALLPAPER={}\n", shell_quote(variable(variables, "wallpaper")));
+        for (name, c) in all_colors(colorscheme) {
+            out.push_str(&format!("export {}='{}'\n", identifier(&name).to_uppercase(), hex(&c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// Quotes a value for fish, which does allow `\'` and `\\` inside single quotes.
+fn fish_quote(value: &str) -> String {
+    return format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// `colors.fish`, the variables of `colors.sh` for fish, which can't source that.
+struct Fish;
+
+impl Exporter for Fish {
+    fn default_path(&self) -> &'static str {
+        "colors.fish"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = format!("set -gx WALLPAPER {}\n", fish_quote(variable(variables, "wallpaper")));
+        for (name, c) in all_colors(colorscheme) {
+            out.push_str(&format!("set -gx {} '{}'\n", identifier(&name).to_uppercase(), hex(&c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `base16-scheme.yaml` in the base16 scheme format, with the same role
+/// mapping as the `@base00`-`@base0F` placeholders.
+struct Base16;
+
+impl Exporter for Base16 {
+    fn default_path(&self) -> &'static str {
+        "base16-scheme.yaml"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let name = Path::new(variable(variables, "wallpaper"))
+                .file_stem()
+                .map_or("pal".to_string(), |stem| format!("pal {}", stem.to_string_lossy()));
+        let mut out = format!("scheme: \"{}\"\nauthor: \"pal\"\n", json_escape(&name));
+        for i in 0..16 {
+            if let Some(c) = colorscheme.base16(i) {
+                out.push_str(&format!("base{:02X}: \"{}\"\n", i, &hex(&c)[1..]));
+            }
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `colors.ase`, Adobe's swatch exchange format. Big endian: a header with
+/// the block count, then one block per color holding its UTF-16 name, the
+/// `RGB ` model, three floats and the swatch type.
+struct Ase;
+
+impl Exporter for Ase {
+    fn default_path(&self) -> &'static str {
+        "colors.ase"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        const COLOR_ENTRY: u16 = 0x0001;
+        const NORMAL_SWATCH: u16 = 2;
+
+        let colors = all_colors(colorscheme);
+        let mut out = Vec::new();
+        out.extend_from_slice(b"ASEF");
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&0u16.to_be_bytes());
+        out.extend_from_slice(&(colors.len() as u32).to_be_bytes());
+
+        for (name, c) in colors {
+            let name: Vec<u16> = name.encode_utf16().chain([0]).collect();
+
+            let mut block = Vec::new();
+            block.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            for unit in name {
+                block.extend_from_slice(&unit.to_be_bytes());
+            }
+            block.extend_from_slice(b"RGB ");
+            for channel in [c.r, c.g, c.b] {
+                block.extend_from_slice(&(channel as f32 / 255.0).to_be_bytes());
+            }
+            block.extend_from_slice(&NORMAL_SWATCH.to_be_bytes());
+
+            out.extend_from_slice(&COLOR_ENTRY.to_be_bytes());
+            out.extend_from_slice(&(block.len() as u32).to_be_bytes());
+            out.extend_from_slice(&block);
+        }
+        return out
+    }
+}
+
+/// Termux's `colors.properties`.
+struct Termux;
+
+impl Exporter for Termux {
+    fn default_path(&self) -> &'static str {
+        "colors.properties"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut out = String::new();
+        out.push_str(&format!("background={}\n", hex(&colorscheme.background)));
+        out.push_str(&format!("foreground={}\n", hex(&colorscheme.foreground)));
+        out.push_str(&format!("cursor={}\n", hex(&colorscheme.foreground)));
+        for (i, c) in colorscheme.palette.iter().enumerate() {
+            out.push_str(&format!("color{}={}\n", i, hex(c)));
+        }
+        return out.into_bytes()
+    }
+
+    fn reload(&self, _path: &Path, destination: Option<&Path>) {
+        // Termux only reads its own copy, one anywhere else changes nothing
+        if destination.is_none_or(|d| Some(d) != home_path(".termux/colors.properties").as_deref()) {
+            return
+        }
+        // Only there when running inside Termux
+        let _ = Command::new("termux-reload-settings").status();
+    }
+}
+
+/// ANSI names of color0-15, as terminals that name them spell them.
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Palette color `i` of the 16 ANSI ones, a missing bright color is derived
+/// from its normal one.
+fn ansi(colorscheme: &Colorscheme, i: usize) -> Color {
+    if let Some(c) = colorscheme.palette.get(i) {
+        return *c
+    }
+    return match colorscheme.palette.get(i % 8) {
+        Some(c) if i >= 8 => c.brightened(),
+        _                 => colorscheme.foreground,
+    }
+}
+
+/// Background of selected text, the same as the `surface2` role.
+fn selection(colorscheme: &Colorscheme) -> Color {
+    return colorscheme.background.mix(&colorscheme.foreground, 0.28)
+}
+
+/// `windows-terminal.json`, an entry for the `schemes` list of Windows
+/// Terminal's settings.json.
+struct WindowsTerminal;
+
+impl Exporter for WindowsTerminal {
+    fn default_path(&self) -> &'static str {
+        "windows-terminal.json"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut entries = vec![
+            ("name".to_string(),                "pal".to_string()),
+            ("background".to_string(),          hex(&colorscheme.background)),
+            ("foreground".to_string(),          hex(&colorscheme.foreground)),
+            ("cursorColor".to_string(),         hex(&colorscheme.foreground)),
+            ("selectionBackground".to_string(), hex(&selection(colorscheme))),
+        ];
+        for i in 0..16 {
+            // Windows Terminal calls magenta purple
+            let name = match ANSI_NAMES[i % 8] { "magenta" => "purple", name => name };
+            let name = if i < 8 { name.to_string() } else { format!("bright{}{}", name[..1].to_uppercase(), &name[1..]) };
+            entries.push((name, hex(&ansi(colorscheme, i))));
+        }
+
+        let entries: Vec<String> = entries.iter().map(|(key, value)| format!("    \"{}\": \"{}\"", key, value)).collect();
+        return format!("{{\n{}\n}}\n", entries.join(",\n")).into_bytes()
+    }
+}
+
+/// `alacritty.toml`, the `[colors]` tables of an Alacritty theme.
+struct Alacritty;
+
+impl Exporter for Alacritty {
+    fn default_path(&self) -> &'static str {
+        "alacritty.toml"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = hex(&colorscheme.background);
+        let fg = hex(&colorscheme.foreground);
+        let mut out = String::new();
+        out.push_str(&format!("[colors.primary]\nbackground = \"{}\"\nforeground = \"{}\"\n\n", bg, fg));
+        out.push_str(&format!("[colors.cursor]\ntext = \"{}\"\ncursor = \"{}\"\n\n", bg, fg));
+        out.push_str(&format!("[colors.selection]\ntext = \"{}\"\nbackground = \"{}\"\n", fg, hex(&selection(colorscheme))));
+        for (table, offset) in [("normal", 0), ("bright", 8)] {
+            out.push_str(&format!("\n[colors.{}]\n", table));
+            for (i, name) in ANSI_NAMES.iter().enumerate() {
+                out.push_str(&format!("{} = \"{}\"\n", name, hex(&ansi(colorscheme, offset + i))));
+            }
+        }
+        return out.into_bytes()
+    }
+}
+
+/// A named color such as `accent` or `error`, palette color `fallback` for
+/// schemes without it.
+fn role(colorscheme: &Colorscheme, name: &str, fallback: usize) -> Color {
+    return colorscheme.named.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| *c)
+            .unwrap_or_else(|| ansi(colorscheme, fallback))
+}
+
+/// The foreground or background, whichever is more readable on `c`.
+fn text_on(colorscheme: &Colorscheme, c: &Color) -> Color {
+    let (bg, fg) = (colorscheme.background, colorscheme.foreground);
+    return if c.contrast_ratio(&bg) >= c.contrast_ratio(&fg) { bg } else { fg }
+}
+
+/// `kitty-theme.conf`, a kitty theme with tab and url colors from the accent.
+struct Kitty;
+
+impl Exporter for Kitty {
+    fn default_path(&self) -> &'static str {
+        "kitty-theme.conf"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let mut entries = vec![
+            ("background".to_string(),              hex(&bg)),
+            ("foreground".to_string(),              hex(&fg)),
+            ("cursor".to_string(),                  hex(&fg)),
+            ("cursor_text_color".to_string(),       hex(&bg)),
+            ("selection_background".to_string(),    hex(&selection(colorscheme))),
+            ("selection_foreground".to_string(),    hex(&fg)),
+            ("url_color".to_string(),               hex(&accent)),
+            ("active_border_color".to_string(),     hex(&accent)),
+            ("inactive_border_color".to_string(),   hex(&selection(colorscheme))),
+            ("active_tab_background".to_string(),   hex(&accent)),
+            ("active_tab_foreground".to_string(),   hex(&text_on(colorscheme, &accent))),
+            ("inactive_tab_background".to_string(), hex(&bg.mix(&fg, 0.12))),
+            ("inactive_tab_foreground".to_string(), hex(&bg.mix(&fg, 0.70))),
+            ("tab_bar_background".to_string(),      hex(&bg)),
+        ];
+        for i in 0..16 {
+            entries.push((format!("color{}", i), hex(&ansi(colorscheme, i))));
+        }
+
+        let mut out = String::new();
+        for (key, value) in entries {
+            out.push_str(&format!("{:<24}{}\n", key, value));
+        }
+        return out.into_bytes()
+    }
+
+    fn reload(&self, _path: &Path, _destination: Option<&Path>) {
+        // kitty rereads kitty.conf (and what it includes) on SIGUSR1
+        let _ = Command::new("pkill").args(["-USR1", "-x", "kitty"]).status();
+    }
+}
+
+/// `foot-colors.ini`, foot's `[colors]` section, hex without the `#`.
+struct Foot;
+
+impl Exporter for Foot {
+    fn default_path(&self) -> &'static str {
+        "foot-colors.ini"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bare = |c: &Color| hex(c)[1..].to_string();
+        let bg = bare(&colorscheme.background);
+        let fg = bare(&colorscheme.foreground);
+        let mut out = format!("[cursor]\ncolor={} {}\n\n[colors]\nalpha=1.0\n", bg, fg);
+        out.push_str(&format!("background={}\nforeground={}\n", bg, fg));
+        for i in 0..8 {
+            out.push_str(&format!("regular{}={}\n", i, bare(&ansi(colorscheme, i))));
+        }
+        for i in 0..8 {
+            out.push_str(&format!("bright{}={}\n", i, bare(&ansi(colorscheme, i + 8))));
+        }
+        out.push_str(&format!("selection-foreground={}\nselection-background={}\n", fg, bare(&selection(colorscheme))));
+        return out.into_bytes()
+    }
+}
+
+/// `wezterm.toml`, a WezTerm color scheme file named `pal`.
+struct WezTerm;
+
+impl Exporter for WezTerm {
+    fn default_path(&self) -> &'static str {
+        "wezterm.toml"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = hex(&colorscheme.background);
+        let fg = hex(&colorscheme.foreground);
+        let list = |offset: usize| (offset..offset + 8).map(|i| format!("\"{}\"", hex(&ansi(colorscheme, i)))).collect::<Vec<_>>().join(", ");
+
+        let mut out = String::from("[colors]\n");
+        out.push_str(&format!("background = \"{}\"\nforeground = \"{}\"\n", bg, fg));
+        out.push_str(&format!("cursor_bg = \"{}\"\ncursor_fg = \"{}\"\ncursor_border = \"{}\"\n", fg, bg, fg));
+        out.push_str(&format!("selection_bg = \"{}\"\nselection_fg = \"{}\"\n", hex(&selection(colorscheme)), fg));
+        out.push_str(&format!("ansi = [{}]\nbrights = [{}]\n", list(0), list(8)));
+        out.push_str("\n[metadata]\nname = \"pal\"\n");
+        return out.into_bytes()
+    }
+}
+
+/// `pal.colorscheme`, a Konsole color scheme, colors given as `r,g,b`.
+struct Konsole;
+
+impl Exporter for Konsole {
+    fn default_path(&self) -> &'static str {
+        "pal.colorscheme"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let mut sections = vec![
+            ("Background".to_string(),        colorscheme.background),
+            ("BackgroundIntense".to_string(), colorscheme.background),
+            ("Foreground".to_string(),        colorscheme.foreground),
+            ("ForegroundIntense".to_string(), colorscheme.foreground.brightened()),
+        ];
+        for i in 0..8 {
+            sections.push((format!("Color{}", i), ansi(colorscheme, i)));
+            sections.push((format!("Color{}Intense", i), ansi(colorscheme, i + 8)));
+        }
+
+        let mut out = String::new();
+        for (section, c) in sections {
+            out.push_str(&format!("[{}]\nColor={},{},{}\n\n", section, c.r, c.g, c.b));
+        }
+        out.push_str("[General]\nDescription=pal\nOpacity=1\n");
+        return out.into_bytes()
+    }
+}
+
+/// `gtk.css`, `@define-color`s for the named colors of libadwaita and the
+/// default GTK themes.
+struct Gtk;
+
+impl Exporter for Gtk {
+    fn default_path(&self) -> &'static str {
+        "gtk.css"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let error = role(colorscheme, "error", 1);
+        // Views sit slightly above the window, headerbars and sidebars slightly below
+        let view = bg.mix(&fg, 0.04);
+        let chrome = bg.mix(&fg, 0.08);
+        let colors = [
+            ("accent_color",            accent),
+            ("accent_bg_color",         accent),
+            ("accent_fg_color",         text_on(colorscheme, &accent)),
+            ("destructive_color",       error),
+            ("destructive_bg_color",    error),
+            ("destructive_fg_color",    text_on(colorscheme, &error)),
+            ("success_color",           role(colorscheme, "success", 2)),
+            ("warning_color",           role(colorscheme, "warning", 3)),
+            ("error_color",             error),
+            ("window_bg_color",         bg),
+            ("window_fg_color",         fg),
+            ("view_bg_color",           view),
+            ("view_fg_color",           fg),
+            ("headerbar_bg_color",      chrome),
+            ("headerbar_fg_color",      fg),
+            ("sidebar_bg_color",        chrome),
+            ("sidebar_fg_color",        fg),
+            ("card_bg_color",           view),
+            ("card_fg_color",           fg),
+            ("dialog_bg_color",         bg),
+            ("dialog_fg_color",         fg),
+            ("popover_bg_color",        view),
+            ("popover_fg_color",        fg),
+            // GTK 3 themes
+            ("theme_bg_color",          bg),
+            ("theme_fg_color",          fg),
+            ("theme_base_color",        view),
+            ("theme_text_color",        fg),
+            ("theme_selected_bg_color", accent),
+            ("theme_selected_fg_color", text_on(colorscheme, &accent)),
+        ];
+
+        let mut out = String::new();
+        for (name, c) in colors {
+            out.push_str(&format!("@define-color {} {};\n", name, hex(&c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `qtct-colors.conf`, a qt5ct/qt6ct color scheme: one list of `#aarrggbb`
+/// per color group, in `QPalette::ColorRole` order.
+struct Qtct;
+
+impl Exporter for Qtct {
+    fn default_path(&self) -> &'static str {
+        "qtct-colors.conf"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let black = Color::default();
+        let group = |text: Color| {
+            let roles = [
+                text,                            // WindowText
+                bg.mix(&fg, 0.12),               // Button
+                bg.mix(&fg, 0.40),               // Light
+                bg.mix(&fg, 0.28),               // Midlight
+                bg.mix(&black, 0.5),             // Dark
+                bg.mix(&fg, 0.20),               // Mid
+                text,                            // Text
+                text_on(colorscheme, &fg),       // BrightText
+                text,                            // ButtonText
+                bg.mix(&fg, 0.04),               // Base
+                bg,                              // Window
+                black,                           // Shadow
+                accent,                          // Highlight
+                text_on(colorscheme, &accent),   // HighlightedText
+                accent,                          // Link
+                role(colorscheme, "accent2", 5), // LinkVisited
+                bg.mix(&fg, 0.08),               // AlternateBase
+                bg,                              // NoRole
+                bg.mix(&fg, 0.12),               // ToolTipBase
+                text,                            // ToolTipText
+                bg.mix(&text, 0.70),             // PlaceholderText
+            ];
+            return roles.iter().map(|c| format!("#ff{}", &hex(c)[1..])).collect::<Vec<_>>().join(", ")
+        };
+
+        let dimmed = bg.mix(&fg, 0.5);
+        let mut out = String::from("[ColorScheme]\n");
+        out.push_str(&format!("active_colors={}\n", group(fg)));
+        out.push_str(&format!("disabled_colors={}\n", group(dimmed)));
+        out.push_str(&format!("inactive_colors={}\n", group(fg)));
+        return out.into_bytes()
+    }
+}
+
+/// `dunst.conf`, a dunstrc drop-in with colors for every urgency.
+struct Dunst;
+
+impl Exporter for Dunst {
+    fn default_path(&self) -> &'static str {
+        "dunst.conf"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let urgencies = [
+            ("urgency_low",      bg.mix(&fg, 0.70), selection(colorscheme)),
+            ("urgency_normal",   fg,                role(colorscheme, "accent", 4)),
+            ("urgency_critical", fg,                role(colorscheme, "error", 1)),
+        ];
+
+        let mut out = String::from("[global]\nseparator_color = frame\n");
+        for (section, text, frame) in urgencies {
+            out.push_str(&format!("\n[{}]\nbackground = \"{}\"\nforeground = \"{}\"\nframe_color = \"{}\"\n", section, hex(&bg), hex(&text), hex(&frame)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `pal.vim`, a colorscheme for Vim and Neovim with the UI and basic syntax
+/// groups mapped to the scheme's roles.
+struct Vim;
+
+impl Exporter for Vim {
+    fn default_path(&self) -> &'static str {
+        "pal.vim"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let light = bg.luminance > fg.luminance;
+        let accent = role(colorscheme, "accent", 4);
+        let (red, green, yellow) = (role(colorscheme, "red", 1), role(colorscheme, "green", 2), role(colorscheme, "yellow", 3));
+        let (blue, magenta, cyan) = (role(colorscheme, "blue", 4), role(colorscheme, "magenta", 5), role(colorscheme, "cyan", 6));
+        let error = role(colorscheme, "error", 1);
+        let warning = role(colorscheme, "warning", 3);
+        let surface = bg.mix(&fg, 0.08);
+        let subtle = bg.mix(&fg, 0.40);
+        let dimmed = bg.mix(&fg, 0.70);
+
+        let groups = [
+            ("Normal",       Some(fg),      Some(bg),                     ""),
+            ("NormalFloat",  Some(fg),      Some(surface),                ""),
+            ("Visual",       None,          Some(selection(colorscheme)), ""),
+            ("Cursor",       Some(bg),      Some(fg),                     ""),
+            ("CursorLine",   None,          Some(surface),                "NONE"),
+            ("CursorLineNr", Some(accent),  Some(surface),                "bold"),
+            ("LineNr",       Some(subtle),  None,                         ""),
+            ("SignColumn",   None,          Some(bg),                     ""),
+            ("NonText",      Some(subtle),  None,                         ""),
+            ("VertSplit",    Some(subtle),  None,                         "NONE"),
+            ("WinSeparator", Some(subtle),  None,                         "NONE"),
+            ("StatusLine",   Some(fg),      Some(bg.mix(&fg, 0.20)),      "NONE"),
+            ("StatusLineNC", Some(dimmed),  Some(surface),                "NONE"),
+            ("TabLine",      Some(dimmed),  Some(surface),                "NONE"),
+            ("TabLineSel",   Some(bg),      Some(accent),                 "bold"),
+            ("TabLineFill",  None,          Some(surface),                "NONE"),
+            ("Pmenu",        Some(fg),      Some(surface),                ""),
+            ("PmenuSel",     Some(bg),      Some(accent),                 ""),
+            ("PmenuSbar",    None,          Some(surface),                ""),
+            ("PmenuThumb",   None,          Some(subtle),                 ""),
+            ("Search",       Some(bg),      Some(yellow),                 ""),
+            ("IncSearch",    Some(bg),      Some(accent),                 ""),
+            ("MatchParen",   Some(accent),  None,                         "bold"),
+            ("ErrorMsg",     Some(error),   None,                         "bold"),
+            ("WarningMsg",   Some(warning), None,                         ""),
+            ("Comment",      Some(subtle),  None,                         "italic"),
+            ("Constant",     Some(magenta), None,                         ""),
+            ("String",       Some(green),   None,                         ""),
+            ("Identifier",   Some(blue),    None,                         "NONE"),
+            ("Function",     Some(blue),    None,                         ""),
+            ("Statement",    Some(red),     None,                         "NONE"),
+            ("PreProc",      Some(cyan),    None,                         ""),
+            ("Type",         Some(yellow),  None,                         "NONE"),
+            ("Special",      Some(cyan),    None,                         ""),
+            ("Error",        Some(error),   None,                         "bold"),
+            ("Todo",         Some(accent),  None,                         "bold"),
+        ];
+
+        let mut out = String::from("\" Generated by pal\nhighlight clear\nif exists('syntax_on')\n  syntax reset\nendif\n");
+        out.push_str(&format!("set background={}\nlet g:colors_name = 'pal'\n\n", if light { "light" } else { "dark" }));
+
+        let colors: Vec<String> = (0..16).map(|i| hex(&ansi(colorscheme, i))).collect();
+        let quoted: Vec<String> = colors.iter().map(|c| format!("'{}'", c)).collect();
+        out.push_str(&format!("let g:terminal_ansi_colors = [{}]\n", quoted.join(", ")));
+        for (i, c) in colors.iter().enumerate() {
+            out.push_str(&format!("let g:terminal_color_{} = '{}'\n", i, c));
+        }
+        out.push('\n');
+
+        for (group, fg, bg, attr) in groups {
+            let mut line = format!("highlight {}", group);
+            if let Some(c) = fg {
+                line.push_str(&format!(" guifg={}", hex(&c)));
+            }
+            if let Some(c) = bg {
+                line.push_str(&format!(" guibg={}", hex(&c)));
+            }
+            if !attr.is_empty() {
+                line.push_str(&format!(" gui={} cterm={}", attr, attr));
+            }
+            out.push_str(&line);
+            out.push('\n');
+        }
+        return out.into_bytes()
+    }
+}
+
+/// TextMate scopes with their colors and font style, for the themes of
+/// editors that use TextMate grammars.
+fn token_colors(colorscheme: &Colorscheme) -> [(&'static str, Color, &'static str); 8] {
+    let comment = colorscheme.background.mix(&colorscheme.foreground, 0.40);
+    return [
+        ("comment",                                      comment,                         "italic"),
+        ("string",                                       role(colorscheme, "green", 2),   ""),
+        ("constant.numeric, constant.language",          role(colorscheme, "magenta", 5), ""),
+        ("keyword, storage",                             role(colorscheme, "red", 1),     ""),
+        ("entity.name.function, support.function",       role(colorscheme, "blue", 4),    ""),
+        ("entity.name.type, support.type, storage.type", role(colorscheme, "yellow", 3),  ""),
+        ("variable.parameter, variable.other.constant",  role(colorscheme, "cyan", 6),    ""),
+        ("invalid",                                      role(colorscheme, "error", 1),   ""),
+    ]
+}
+
+/// `vscode-theme.json`, a VS Code color theme with the workbench and basic
+/// token colors.
+struct VsCode;
+
+impl Exporter for VsCode {
+    fn default_path(&self) -> &'static str {
+        "vscode-theme.json"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let light = bg.luminance > fg.luminance;
+        let accent = role(colorscheme, "accent", 4);
+        let on_accent = text_on(colorscheme, &accent);
+        let surface = bg.mix(&fg, 0.04);
+        let chrome = bg.mix(&fg, 0.08);
+        let border = bg.mix(&fg, 0.20);
+        let subtle = bg.mix(&fg, 0.40);
+        let dimmed = bg.mix(&fg, 0.70);
+
+        let workbench = [
+            ("focusBorder",                       accent),
+            ("foreground",                        fg),
+            ("editor.background",                 bg),
+            ("editor.foreground",                 fg),
+            ("editor.lineHighlightBackground",    surface),
+            ("editor.selectionBackground",        selection(colorscheme)),
+            ("editorCursor.foreground",           fg),
+            ("editorLineNumber.foreground",       subtle),
+            ("editorLineNumber.activeForeground", accent),
+            ("editorWidget.background",           chrome),
+            ("editorGroupHeader.tabsBackground",  chrome),
+            ("tab.activeBackground",              bg),
+            ("tab.activeForeground",              fg),
+            ("tab.inactiveBackground",            chrome),
+            ("tab.inactiveForeground",            dimmed),
+            ("tab.activeBorderTop",               accent),
+            ("activityBar.background",            chrome),
+            ("activityBar.foreground",            fg),
+            ("activityBarBadge.background",       accent),
+            ("activityBarBadge.foreground",       on_accent),
+            ("sideBar.background",                chrome),
+            ("sideBar.foreground",                dimmed),
+            ("sideBarSectionHeader.background",   chrome),
+            ("titleBar.activeBackground",         chrome),
+            ("titleBar.activeForeground",         fg),
+            ("statusBar.background",              chrome),
+            ("statusBar.foreground",              dimmed),
+            ("panel.background",                  bg),
+            ("panel.border",                      border),
+            ("list.activeSelectionBackground",    selection(colorscheme)),
+            ("list.hoverBackground",              surface),
+            ("input.background",                  surface),
+            ("dropdown.background",               chrome),
+            ("button.background",                 accent),
+            ("button.foreground",                 on_accent),
+            ("badge.background",                  accent),
+            ("badge.foreground",                  on_accent),
+            ("terminal.background",               bg),
+            ("terminal.foreground",               fg),
+        ];
+        let mut workbench: Vec<(String, Color)> = workbench.iter().map(|(key, c)| (key.to_string(), *c)).collect();
+        for i in 0..16 {
+            let name = ANSI_NAMES[i % 8];
+            let name = format!("terminal.ansi{}{}{}", if i < 8 { "" } else { "Bright" }, name[..1].to_uppercase(), &name[1..]);
+            workbench.push((name, ansi(colorscheme, i)));
+        }
+
+        let tokens = token_colors(colorscheme);
+
+        let workbench: Vec<String> = workbench.iter().map(|(key, c)| format!("        \"{}\": \"{}\"", key, hex(c))).collect();
+        let tokens: Vec<String> = tokens.iter().map(|(scope, c, style)| {
+            let style = if style.is_empty() { String::new() } else { format!(", \"fontStyle\": \"{}\"", style) };
+            format!("        {{ \"scope\": \"{}\", \"settings\": {{ \"foreground\": \"{}\"{} }} }}", scope, hex(c), style)
+        }).collect();
+
+        let mut out = String::from("{\n    \"name\": \"pal\",\n");
+        out.push_str(&format!("    \"type\": \"{}\",\n", if light { "light" } else { "dark" }));
+        out.push_str(&format!("    \"colors\": {{\n{}\n    }},\n", workbench.join(",\n")));
+        out.push_str(&format!("    \"tokenColors\": [\n{}\n    ]\n}}\n", tokens.join(",\n")));
+        return out.into_bytes()
+    }
+}
+
+/// `sway-colors`, window colors for sway and i3 plus variables for the bar.
+struct Sway;
+
+impl Exporter for Sway {
+    fn default_path(&self) -> &'static str {
+        "sway-colors"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let urgent = role(colorscheme, "error", 1);
+        let inactive = bg.mix(&fg, 0.12);
+        let variables = [
+            ("background", bg),
+            ("foreground", fg),
+            ("accent",     accent),
+            ("inactive",   inactive),
+            ("urgent",     urgent),
+        ];
+        // border, background, text, indicator, child_border
+        let classes = [
+            ("focused",          [accent, accent, text_on(colorscheme, &accent), role(colorscheme, "accent2", 5), accent]),
+            ("focused_inactive", [inactive, inactive, fg, inactive, inactive]),
+            ("unfocused",        [bg, bg, bg.mix(&fg, 0.70), bg, bg]),
+            ("urgent",           [urgent, urgent, text_on(colorscheme, &urgent), urgent, urgent]),
+            ("placeholder",      [bg, bg, fg, bg, bg]),
+        ];
+
+        let mut out = String::new();
+        for (name, c) in variables {
+            out.push_str(&format!("set $pal_{} {}\n", name, hex(&c)));
+        }
+        out.push('\n');
+        for (class, colors) in classes {
+            let colors: Vec<String> = colors.iter().map(hex).collect();
+            out.push_str(&format!("{:<24}{}\n", format!("client.{}", class), colors.join(" ")));
+        }
+        out.push_str(&format!("{:<24}{}\n", "client.background", hex(&bg)));
+        return out.into_bytes()
+    }
+}
+
+/// `hyprland-colors.conf`, Hyprland variables and border colors.
+struct Hyprland;
+
+impl Exporter for Hyprland {
+    fn default_path(&self) -> &'static str {
+        "hyprland-colors.conf"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let rgba = |c: &Color, alpha: u8| format!("rgba({}{:02x})", &hex(c)[1..], alpha);
+        let mut out = String::new();
+        for (name, c) in all_colors(colorscheme) {
+            out.push_str(&format!("${} = {}\n", name, rgba(&c, 0xff)));
+        }
+
+        let accent = role(colorscheme, "accent", 4);
+        let accent2 = role(colorscheme, "accent2", 5);
+        let inactive = colorscheme.background.mix(&colorscheme.foreground, 0.20);
+        out.push_str("\ngeneral {\n");
+        out.push_str(&format!("    col.active_border = {} {} 45deg\n", rgba(&accent, 0xff), rgba(&accent2, 0xff)));
+        out.push_str(&format!("    col.inactive_border = {}\n", rgba(&inactive, 0xaa)));
+        out.push_str("}\n");
+        return out.into_bytes()
+    }
+}
+
+/// `tmux-colors.conf`, tmux options for the status bar, pane borders and messages.
+struct Tmux;
+
+impl Exporter for Tmux {
+    fn default_path(&self) -> &'static str {
+        "tmux-colors.conf"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = hex(&colorscheme.background);
+        let fg = hex(&colorscheme.foreground);
+        let accent = role(colorscheme, "accent", 4);
+        let on_accent = hex(&text_on(colorscheme, &accent));
+        let accent = hex(&accent);
+        let surface = hex(&colorscheme.background.mix(&colorscheme.foreground, 0.12));
+        let dimmed = hex(&colorscheme.background.mix(&colorscheme.foreground, 0.70));
+        let options = [
+            ("status-style",                format!("bg={},fg={}", bg, fg)),
+            ("window-status-style",         format!("fg={}", dimmed)),
+            ("window-status-current-style", format!("bg={},fg={}", accent, on_accent)),
+            ("pane-border-style",           format!("fg={}", surface)),
+            ("pane-active-border-style",    format!("fg={}", accent)),
+            ("message-style",               format!("bg={},fg={}", surface, fg)),
+            ("message-command-style",       format!("bg={},fg={}", surface, fg)),
+            ("mode-style",                  format!("bg={},fg={}", hex(&selection(colorscheme)), fg)),
+            ("display-panes-colour",        surface),
+            ("display-panes-active-colour", accent.clone()),
+            ("clock-mode-colour",           accent),
+        ];
+
+        let mut out = String::new();
+        for (option, value) in options {
+            // Quoted, a bare # would start a comment
+            out.push_str(&format!("set -g {} \"{}\"\n", option, value));
+        }
+        return out.into_bytes()
+    }
+
+    fn reload(&self, _path: &Path, destination: Option<&Path>) {
+        // Only a copy mapped into tmux's config is one it's meant to read
+        let Some(destination) = destination else { return };
+        // Fails quietly when no tmux server is running
+        let _ = Command::new("tmux").arg("source-file").arg(destination).output();
+    }
+}
+
+/// `discord.css`, a Vencord/BetterDiscord theme overriding Discord's color
+/// variables in both its dark and light themes.
+struct Discord;
+
+impl Exporter for Discord {
+    fn default_path(&self) -> &'static str {
+        "discord.css"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let properties = [
+            ("background-primary",           bg),
+            ("background-secondary",         bg.mix(&fg, 0.04)),
+            ("background-secondary-alt",     bg.mix(&fg, 0.08)),
+            ("background-tertiary",          bg.mix(&fg, 0.08)),
+            ("background-floating",          bg.mix(&fg, 0.04)),
+            ("background-accent",            accent),
+            ("background-modifier-hover",    bg.mix(&fg, 0.12)),
+            ("background-modifier-active",   bg.mix(&fg, 0.20)),
+            ("background-modifier-selected", selection(colorscheme)),
+            ("channeltextarea-background",   bg.mix(&fg, 0.08)),
+            ("text-normal",                  fg),
+            ("text-muted",                   bg.mix(&fg, 0.70)),
+            ("text-link",                    accent),
+            ("header-primary",               fg),
+            ("header-secondary",             bg.mix(&fg, 0.70)),
+            ("interactive-normal",           bg.mix(&fg, 0.70)),
+            ("interactive-hover",            fg),
+            ("interactive-active",           fg),
+            ("interactive-muted",            bg.mix(&fg, 0.40)),
+            ("channels-default",             bg.mix(&fg, 0.70)),
+            ("brand-experiment",             accent),
+            ("brand-500",                    accent),
+            ("status-danger",                role(colorscheme, "error", 1)),
+            ("status-warning",               role(colorscheme, "warning", 3)),
+            ("status-positive",              role(colorscheme, "success", 2)),
+        ];
+
+        let mut out = String::from("/**\n * @name pal\n * @description Colors generated by pal\n * @author pal\n */\n\n");
+        out.push_str(":root, .theme-dark, .theme-light {\n");
+        for (name, c) in properties {
+            out.push_str(&format!("    --{}: {};\n", name, hex(&c)));
+        }
+        out.push_str("}\n");
+        return out.into_bytes()
+    }
+}
+
+/// `pywalfox.json`, the same colors.json with all 16 colors pywal always
+/// has, meant to take pywal's place for the Pywalfox Firefox extension.
+struct Pywalfox;
+
+impl Exporter for Pywalfox {
+    fn default_path(&self) -> &'static str {
+        "pywalfox.json"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<u8> {
+        let mut full = colorscheme.clone();
+        full.palette = (0..16).map(|i| ansi(colorscheme, i)).collect();
+        return Json.render(&full, variables)
+    }
+
+    fn reload(&self, path: &Path, _destination: Option<&Path>) {
+        // Pywalfox reads pywal's file, so there's only something new to send
+        // once this one has been mapped there
+        let Ok(home) = env::var("HOME") else { return };
+        let wal_colors = fs::read(Path::new(&home).join(".cache/wal/colors.json"));
+        match (wal_colors, fs::read(path)) {
+            (Ok(wal_colors), Ok(colors)) if wal_colors == colors => {}
+            _ => return,
+        }
+        // Tells the native messaging host to send the new colors to Firefox
+        let _ = Command::new("pywalfox").arg("update").output();
+    }
+}
+
+/// `polybar-colors.ini`, polybar's usual `[colors]` section from the roles.
+struct Polybar;
+
+impl Exporter for Polybar {
+    fn default_path(&self) -> &'static str {
+        "polybar-colors.ini"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let colors = [
+            ("background",     bg),
+            ("background-alt", bg.mix(&fg, 0.12)),
+            ("foreground",     fg),
+            ("primary",        role(colorscheme, "accent", 4)),
+            ("secondary",      role(colorscheme, "accent2", 5)),
+            ("alert",          role(colorscheme, "error", 1)),
+            ("disabled",       bg.mix(&fg, 0.40)),
+        ];
+
+        let mut out = String::from("[colors]\n");
+        for (name, c) in colors {
+            out.push_str(&format!("{} = {}\n", name, hex(&c)));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `pal.theme`, a btop theme whose graph gradients run from a dim shade of
+/// a role color through the color itself to a tint of it.
+struct Btop;
+
+impl Exporter for Btop {
+    fn default_path(&self) -> &'static str {
+        "pal.theme"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let white = Color::from_hex("#ffffff").unwrap_or(fg);
+        let accent = role(colorscheme, "accent", 4);
+        let accent2 = role(colorscheme, "accent2", 5);
+        let border = bg.mix(&fg, 0.28);
+        let dimmed = bg.mix(&fg, 0.40);
+        let colors = [
+            ("main_bg",     bg),
+            ("main_fg",     fg),
+            ("title",       fg),
+            ("hi_fg",       accent),
+            ("selected_bg", selection(colorscheme)),
+            ("selected_fg", fg),
+            ("inactive_fg", dimmed),
+            ("graph_text",  bg.mix(&fg, 0.70)),
+            ("meter_bg",    bg.mix(&fg, 0.12)),
+            ("proc_misc",   accent2),
+            ("cpu_box",     border),
+            ("mem_box",     border),
+            ("net_box",     border),
+            ("proc_box",    border),
+            ("div_line",    border),
+        ];
+        let gradients = [
+            ("temp",      role(colorscheme, "warning", 3)),
+            ("cpu",       accent),
+            ("free",      role(colorscheme, "success", 2)),
+            ("cached",    role(colorscheme, "info", 6)),
+            ("available", accent2),
+            ("used",      role(colorscheme, "error", 1)),
+            ("download",  accent),
+            ("upload",    accent2),
+            ("process",   accent),
+        ];
+
+        let mut out = String::new();
+        for (name, c) in colors {
+            out.push_str(&format!("theme[{}]=\"{}\"\n", name, hex(&c)));
+        }
+        for (name, c) in gradients {
+            let steps = [("start", bg.mix(&c, 0.5)), ("mid", c), ("end", c.mix(&white, 0.5))];
+            for (step, c) in steps {
+                out.push_str(&format!("theme[{}_{}]=\"{}\"\n", name, step, hex(&c)));
+            }
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `pal.tmTheme`, a TextMate theme as bat reads them.
+struct Bat;
+
+impl Exporter for Bat {
+    fn default_path(&self) -> &'static str {
+        "pal.tmTheme"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let pair = |key: &str, value: &str| format!("<key>{}</key><string>{}</string>", key, value);
+        let globals = [
+            ("background",       bg),
+            ("foreground",       fg),
+            ("caret",            fg),
+            ("lineHighlight",    bg.mix(&fg, 0.08)),
+            ("selection",        selection(colorscheme)),
+            ("gutterForeground", bg.mix(&fg, 0.40)),
+        ];
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n");
+        out.push_str(&format!("<plist version=\"1.0\">\n<dict>\n    {}\n    <key>settings</key>\n    <array>\n", pair("name", "pal")));
+        let globals: Vec<String> = globals.iter().map(|(key, c)| pair(key, &hex(c))).collect();
+        out.push_str(&format!("        <dict><key>settings</key><dict>{}</dict></dict>\n", globals.join("")));
+        for (scope, c, style) in token_colors(colorscheme) {
+            let style = if style.is_empty() { String::new() } else { pair("fontStyle", style) };
+            out.push_str(&format!("        <dict>{}<key>settings</key><dict>{}{}</dict></dict>\n", pair("scope", scope), pair("foreground", &hex(&c)), style));
+        }
+        out.push_str("    </array>\n</dict>\n</plist>\n");
+        return out.into_bytes()
+    }
+
+    fn reload(&self, _path: &Path, _destination: Option<&Path>) {
+        // bat only sees new themes once its cache is rebuilt
+        let _ = Command::new("bat").args(["cache", "--build"]).output();
+    }
+}
+
+/// `delta.gitconfig`, a `[delta "pal"]` feature for git's config.
+struct Delta;
+
+impl Exporter for Delta {
+    fn default_path(&self) -> &'static str {
+        "delta.gitconfig"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let red = role(colorscheme, "red", 1);
+        let green = role(colorscheme, "green", 2);
+        let accent = role(colorscheme, "accent", 4);
+        let options = [
+            ("syntax-theme",             "pal".to_string()),
+            ("minus-style",              format!("syntax \"{}\"", hex(&bg.mix(&red, 0.15)))),
+            ("minus-emph-style",         format!("syntax \"{}\"", hex(&bg.mix(&red, 0.35)))),
+            ("plus-style",               format!("syntax \"{}\"", hex(&bg.mix(&green, 0.15)))),
+            ("plus-emph-style",          format!("syntax \"{}\"", hex(&bg.mix(&green, 0.35)))),
+            ("line-numbers-minus-style", format!("\"{}\"", hex(&red))),
+            ("line-numbers-plus-style",  format!("\"{}\"", hex(&green))),
+            ("line-numbers-zero-style",  format!("\"{}\"", hex(&bg.mix(&fg, 0.40)))),
+            ("file-style",               format!("\"{}\" bold", hex(&accent))),
+            ("hunk-header-style",        format!("file line-number syntax \"{}\"", hex(&bg.mix(&fg, 0.08)))),
+        ];
+
+        let mut out = String::from("[delta \"pal\"]\n");
+        for (option, value) in options {
+            out.push_str(&format!("    {} = {}\n", option, value));
+        }
+        return out.into_bytes()
+    }
+}
+
+/// `spicetify-color.ini`, a Spicetify color scheme, hex without the `#`.
+struct Spicetify;
+
+impl Exporter for Spicetify {
+    fn default_path(&self) -> &'static str {
+        "spicetify-color.ini"
+    }
+
+    fn render(&self, colorscheme: &Colorscheme, _variables: &[(String, String)]) -> Vec<u8> {
+        let bg = colorscheme.background;
+        let fg = colorscheme.foreground;
+        let accent = role(colorscheme, "accent", 4);
+        let colors = [
+            ("text",               fg),
+            ("subtext",            bg.mix(&fg, 0.70)),
+            ("main",               bg),
+            ("sidebar",            bg.mix(&fg, 0.04)),
+            ("player",             bg.mix(&fg, 0.04)),
+            ("card",               bg.mix(&fg, 0.08)),
+            ("shadow",             Color::default()),
+            ("selected-row",       bg.mix(&fg, 0.70)),
+            ("button",             accent),
+            ("button-active",      accent),
+            ("button-disabled",    bg.mix(&fg, 0.40)),
+            ("tab-active",         bg.mix(&fg, 0.12)),
+            ("notification",       accent),
+            ("notification-error", role(colorscheme, "error", 1)),
+            ("misc",               bg.mix(&fg, 0.40)),
+        ];
+
+        let mut out = String::from("[pal]\n");
+        for (name, c) in colors {
+            out.push_str(&format!("{:<19}= {}\n", name, &hex(&c)[1..]));
+        }
+        return out.into_bytes()
+    }
+
+    fn reload(&self, _path: &Path, destination: Option<&Path>) {
+        // Applying restarts Spotify, only worth it when the colors are mapped
+        // into a theme, `<spicetify config>/Themes/<theme>/color.ini`
+        let in_themes = destination
+                .and_then(|d| d.parent()?.parent())
+                .is_some_and(|themes| {
+                    themes.file_name().is_some_and(|name| name == "Themes")
+                        && themes.parent().and_then(|p| p.file_name()).is_some_and(|name| name.eq_ignore_ascii_case("spicetify"))
+                });
+        if !in_themes {
+            return
+        }
+        // Patches the new colors into Spotify and restarts it
+        let _ = Command::new("spicetify").arg("apply").output();
+    }
+}
+
+pub fn exporter(kind: Export) -> Box<dyn Exporter> {
+    match kind {
+        Export::Sequences       => Box::new(Sequences),
+        Export::Json            => Box::new(Json),
+        Export::Yaml            => Box::new(Yaml),
+        Export::Css             => Box::new(Css),
+        Export::Scss            => Box::new(Scss),
+        Export::Xresources      => Box::new(Xresources),
+        Export::Shell           => Box::new(Shell),
+        Export::Fish            => Box::new(Fish),
+        Export::Base16          => Box::new(Base16),
+        Export::Ase             => Box::new(Ase),
+        Export::Termux          => Box::new(Termux),
+        Export::WindowsTerminal => Box::new(WindowsTerminal),
+        Export::Alacritty       => Box::new(Alacritty),
+        Export::Kitty           => Box::new(Kitty),
+        Export::Foot            => Box::new(Foot),
+        Export::WezTerm         => Box::new(WezTerm),
+        Export::Konsole         => Box::new(Konsole),
+        Export::Gtk             => Box::new(Gtk),
+        Export::Qtct            => Box::new(Qtct),
+        Export::Dunst           => Box::new(Dunst),
+        Export::Vim             => Box::new(Vim),
+        Export::VsCode          => Box::new(VsCode),
+        Export::Sway            => Box::new(Sway),
+        Export::Hyprland        => Box::new(Hyprland),
+        Export::Tmux            => Box::new(Tmux),
+        Export::Discord         => Box::new(Discord),
+        Export::Pywalfox        => Box::new(Pywalfox),
+        Export::Polybar         => Box::new(Polybar),
+        Export::Btop            => Box::new(Btop),
+        Export::Bat             => Box::new(Bat),
+        Export::Delta           => Box::new(Delta),
+        Export::Spicetify       => Box::new(Spicetify),
+    }
+}
+
+/// Renders each requested export, paired with its file name.
+pub fn render_exports(kinds: &[Export], colorscheme: &Colorscheme, variables: &[(String, String)]) -> Vec<(&'static str, Vec<u8>)> {
+    return kinds
+            .iter()
+            .map(|&kind| {
+                let exporter = exporter(kind);
+                (exporter.default_path(), exporter.render(colorscheme, variables))
+            })
+            .collect()
+}
+
+/// Lets the programs behind the exports that changed pick them up. `changed`
+/// pairs each file name with its `targets.toml` destination.
+pub fn reload_changed(kinds: &[Export], changed: &[(String, Option<PathBuf>)], cache_path: &Path) {
+    for &kind in kinds {
+        let exporter = exporter(kind);
+        if let Some((_, destination)) = changed.iter().find(|(name, _)| name == exporter.default_path()) {
+            exporter.reload(&cache_path.join(exporter.default_path()), destination.as_deref());
+        }
+    }
+}
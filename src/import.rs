@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+use crate::cli::Import;
+use crate::colorscheme::{Color, Colorscheme};
+
+fn parse_color(value: &str, path: &Path) -> Color {
+    return Color::from_hex(value).unwrap_or_else(|| {
+        eprintln!("Error: invalid color '{}' in '{}'", value, path.display());
+        exit(1);
+    })
+}
+
+/// Finds the string value of `"key": "..."` in a flat JSON document.
+fn json_string<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let after_key = &content[content.find(&format!("\"{}\"", key))? + key.len() + 2..];
+    let value = after_key.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    return Some(&value[..value.find('"')?])
+}
+
+/// pywal's `colors.json` has `special.background/foreground` and `colors.color0-15`,
+/// its plain `colors` file lists color0-15 one per line with color0 and color15
+/// doubling as background and foreground.
+fn import_pywal(content: &str, path: &Path) -> Colorscheme {
+    if content.trim_start().starts_with('{') {
+        let field = |key: &str| {
+            let value = json_string(content, key).unwrap_or_else(|| {
+                eprintln!("Error: missing '{}' in '{}'", key, path.display());
+                exit(1);
+            });
+            parse_color(value, path)
+        };
+        let palette = (0..16).map(|i| field(&format!("color{}", i))).collect();
+
+        return Colorscheme { palette:    palette,
+                             background: field("background"),
+                             foreground: field("foreground"),
+                             named:      Vec::new(),
+                             monochrome: false }
+    }
+
+    let palette: Vec<Color> = content.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| parse_color(l, path))
+            .collect();
+    if palette.len() < 16 {
+        eprintln!("Error: expected 16 colors in '{}', found {}", path.display(), palette.len());
+        exit(1);
+    }
+
+    return Colorscheme { background: palette[0],
+                         foreground: palette[15],
+                         palette:    palette,
+                         named:      Vec::new(),
+                         monochrome: false }
+}
+
+/// Reads `baseXX: "rrggbb"` keys from base16/base24 YAML, both the flat
+/// layout and the newer one nesting them under `palette:`.
+fn base_colors(content: &str) -> Vec<(String, String)> {
+    return content.lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                let key = key.trim().to_lowercase();
+                if key.len() != 6 || !key.starts_with("base") { return None }
+                let value = value.split_whitespace().next()?.trim_matches(|c| c == '"' || c == '\'');
+                Some((key, value.to_string()))
+            })
+            .collect()
+}
+
+/// Maps base16 slots onto the ANSI layout used by base16-shell; base24
+/// schemes supply their own bright colors in base12-base17.
+fn import_base16(content: &str, path: &Path) -> Colorscheme {
+    let colors = base_colors(content);
+    let base = |slot: &str| colors.iter().find(|(key, _)| key == slot).map(|(_, value)| parse_color(value, path));
+    let required = |slot: &str| base(slot).unwrap_or_else(|| {
+        eprintln!("Error: missing '{}' in '{}'", slot, path.display());
+        exit(1);
+    });
+
+    const NORMAL: [&str; 8] = ["base00", "base08", "base0b", "base0a", "base0d", "base0e", "base0c", "base05"];
+    const BRIGHT: [(&str, &str); 8] = [
+        ("base03", "base03"), ("base12", "base08"), ("base14", "base0b"), ("base13", "base0a"),
+        ("base16", "base0d"), ("base17", "base0e"), ("base15", "base0c"), ("base07", "base07"),
+    ];
+    let mut palette: Vec<Color> = NORMAL.iter().map(|slot| required(slot)).collect();
+    palette.extend(BRIGHT.iter().map(|(base24, base16)| base(base24).unwrap_or_else(|| required(base16))));
+
+    return Colorscheme { palette:    palette,
+                         background: required("base00"),
+                         foreground: required("base05"),
+                         named:      Vec::new(),
+                         monochrome: false }
+}
+
+/// Reads a scheme saved by another tool.
+pub fn import_scheme(kind: Import, path: &Path) -> Colorscheme {
+    let content = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error: could not read '{}': {}", path.display(), e);
+        exit(1);
+    });
+
+    match kind {
+        Import::Pywal  => import_pywal(&content, path),
+        Import::Base16 => import_base16(&content, path),
+    }
+}
@@ -0,0 +1,834 @@
+use std::fs;
+use std::env;
+use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Cursor, Read};
+use std::process::{exit, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use attohttpc::ProxySettings;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, Frames, ImageError, ImageFormat, ImageReader};
+use image::error::{DecodingError, ImageFormatHint};
+use image::metadata::Orientation;
+use image::codecs::gif::GifDecoder;
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+
+use crate::cli::{Args, Crop};
+
+/// Fetches an image, retrying connection errors and 5xx responses with a
+/// growing delay. Proxies come from `http_proxy`/`https_proxy`/`no_proxy`.
+fn get_image_from_url(url: &str, conf: &Args) -> Result<Vec<u8>, String> {
+    let mut last_error = String::new();
+    for attempt in 0..=conf.retries {
+        if attempt > 0 {
+            thread::sleep(Duration::from_secs(attempt as u64));
+        }
+
+        let response = attohttpc::get(url)
+                .timeout(Duration::from_secs(conf.timeout))
+                .connect_timeout(Duration::from_secs(conf.timeout))
+                .read_timeout(Duration::from_secs(conf.timeout))
+                .max_redirections(conf.max_redirects)
+                .follow_redirects(conf.max_redirects > 0)
+                .proxy_settings(ProxySettings::from_env())
+                .send();
+
+        match response {
+            Ok(response) if response.is_success() => {
+                return response.bytes().map_err(|e| e.to_string())
+            }
+            Ok(response) if response.status().is_server_error() => {
+                last_error = format!("server responded with {}", response.status());
+            }
+            Ok(response) => return Err(format!("server responded with {}", response.status())),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    return Err(last_error)
+}
+
+/// Downloads are kept under `downloads_path`, keyed by url, so a scheme cache
+/// miss (e.g. a new `-s`) doesn't fetch the same image again. `--dry-run`
+/// uses the cache but doesn't add to it.
+fn get_cached_image_from_url(url: &str, conf: &Args, downloads_path: &Path) -> Vec<u8> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let download_file_path = downloads_path.join(format!("{:x}", hasher.finish()));
+
+    if let Ok(bytes) = fs::read(&download_file_path) {
+        return bytes
+    }
+
+    let bytes = get_image_from_url(url, conf).unwrap_or_else(|e| {
+        eprintln!("Error: could not download image from '{}': {}", url, e);
+        exit(1);
+    });
+    if !conf.dry_run && fs::write(&download_file_path, &bytes).is_err() {
+        eprintln!("Warning: failed to cache downloaded image");
+    }
+    return bytes
+}
+
+pub fn read_stdin_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes).unwrap_or_else(|e| {
+        eprintln!("Error: could not read image from stdin: {}", e);
+        exit(1);
+    });
+    return bytes
+}
+
+/// How long a pipe on stdin gets to start delivering, `grim - | pal` needs a
+/// moment before its first bytes arrive.
+#[cfg(unix)]
+const STDIN_WAIT_MS: i32 = 200;
+
+/// Whether stdin carries data to use as the image: a non-empty file, or a
+/// pipe with bytes ready shortly after startup. Nothing is read to find out,
+/// so a pipe pal merely inherits (`while read` loops, launchers) is left
+/// alone as long as it's quiet. Terminals, `/dev/null` and the like (cron,
+/// systemd, hotkey daemons) don't count.
+#[cfg(unix)]
+pub fn stdin_has_data() -> bool {
+    use std::os::fd::{AsFd, AsRawFd};
+    use std::os::unix::fs::FileTypeExt;
+
+    let Ok(stdin) = io::stdin().as_fd().try_clone_to_owned() else { return false };
+    let Ok(metadata) = fs::File::from(stdin).metadata() else { return false };
+    let file_type = metadata.file_type();
+    if file_type.is_file() {
+        return metadata.len() > 0
+    }
+    if !file_type.is_fifo() && !file_type.is_socket() {
+        return false
+    }
+
+    let mut poll_fd = libc::pollfd { fd: io::stdin().as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    // SAFETY: a single valid pollfd, stdin stays open for the whole call
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, STDIN_WAIT_MS) };
+    return ready > 0 && poll_fd.revents & libc::POLLIN != 0
+}
+
+#[cfg(not(unix))]
+pub fn stdin_has_data() -> bool {
+    return false
+}
+
+/// Screenshot tools tried in order, each writing a PNG of the whole screen to stdout.
+const SCREEN_BACKENDS: [(&str, &[&str]); 4] = [
+    ("grim",   &["-t", "png", "-"]),
+    ("maim",   &["--format", "png"]),
+    ("import", &["-window", "root", "png:-"]),
+    ("scrot",  &["-"]),
+];
+
+/// Runs external tools in order until one writes something to stdout. The
+/// error names the tools that aren't installed and why the others failed.
+fn run_first_tool(tools: &[(&str, &[&str])]) -> Result<Vec<u8>, String> {
+    let mut missing = Vec::new();
+    let mut failures = Vec::new();
+    for (program, args) in tools {
+        match Command::new(program).args(*args).stdin(Stdio::null()).output() {
+            Ok(output) if output.status.success() && !output.stdout.is_empty() => return Ok(output.stdout),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr.lines().map(str::trim).find(|l| !l.is_empty()).map_or(output.status.to_string(), str::to_string);
+                failures.push(format!("{} failed: {}", program, reason));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => missing.push(*program),
+            Err(e) => failures.push(format!("{}: {}", program, e)),
+        }
+    }
+    if !missing.is_empty() {
+        failures.push(format!("not installed: {}", missing.join(", ")));
+    }
+    return Err(failures.join("; "))
+}
+
+pub fn capture_screen() -> Vec<u8> {
+    return run_first_tool(&SCREEN_BACKENDS).unwrap_or_else(|e| {
+        eprintln!("Error: could not capture the screen ({}), install grim, maim, imagemagick or scrot", e);
+        exit(1);
+    })
+}
+
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None
+    }
+    return Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `swww query` lines look like `eDP-1: 1920x1080, ..., currently displaying: image: /path`
+fn swww_wallpaper() -> Option<String> {
+    let output = command_stdout("swww", &["query"])?;
+    let (_, path) = output.lines().next()?.split_once("image: ")?;
+    return Some(path.trim().to_string())
+}
+
+/// `hyprctl hyprpaper listactive` lines look like `eDP-1 = /path`
+fn hyprpaper_wallpaper() -> Option<String> {
+    let output = command_stdout("hyprctl", &["hyprpaper", "listactive"])?;
+    let (_, path) = output.lines().next()?.split_once(" = ")?;
+    return Some(path.trim().to_string())
+}
+
+/// gsettings prints the uri quoted, e.g. `'file:///path'`, with spaces and
+/// non-ASCII characters percent-encoded
+fn gnome_wallpaper() -> Option<String> {
+    let output = command_stdout("gsettings", &["get", "org.gnome.desktop.background", "picture-uri"])?;
+    let uri = output.trim().trim_matches('\'');
+    let Some(path) = uri.strip_prefix("file://") else { return Some(uri.to_string()) };
+    return String::from_utf8(decode_percent(path)).ok()
+}
+
+/// ~/.fehbg ends with `feh --bg-fill '/path'`, the image is the last quoted argument
+fn feh_wallpaper() -> Option<String> {
+    let home = env::var("HOME").ok()?;
+    let fehbg = fs::read_to_string(Path::new(&home).join(".fehbg")).ok()?;
+    let line = fehbg.lines().rev().find(|l| l.trim_start().starts_with("feh"))?;
+    let path = line.rsplit('\'').nth(1)?;
+    return Some(path.to_string())
+}
+
+/// Finds the current wallpaper from swww, hyprpaper, GNOME or feh.
+pub fn detect_wallpaper() -> Option<String> {
+    return [swww_wallpaper, hyprpaper_wallpaper, gnome_wallpaper, feh_wallpaper]
+            .iter()
+            .filter_map(|source| source())
+            .find(|path| !path.is_empty() && Path::new(path).exists())
+}
+
+/// Picks `max_frames` frames spread evenly over the whole animation, without
+/// holding on to all of them: every `stride`-th frame is kept, and once twice
+/// as many as needed pile up, every other one is dropped and the stride doubles.
+fn spread_frames(frames: Frames, max_frames: usize) -> Result<Vec<DynamicImage>, ImageError> {
+    let mut kept = Vec::new();
+    let mut stride = 1;
+    for (i, frame) in frames.enumerate() {
+        let frame = frame?;
+        if i % stride != 0 { continue }
+
+        kept.push(DynamicImage::ImageRgba8(frame.into_buffer()));
+        if kept.len() >= 2 * max_frames {
+            kept = kept.into_iter().step_by(2).collect();
+            stride *= 2;
+        }
+    }
+
+    let step = (kept.len() as f32 / max_frames as f32).max(1.0);
+    let picked: Vec<usize> = (0..max_frames.min(kept.len()))
+            .map(|i| (i as f32 * step) as usize)
+            .collect();
+
+    return Ok(kept
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| picked.contains(i))
+            .map(|(_, frame)| frame)
+            .collect())
+}
+
+/// HEIF brands used by HEIC photos, found right after `ftyp` in the header.
+const HEIC_BRANDS: [&[u8]; 4] = [b"heic", b"heix", b"hevc", b"hevx"];
+/// Generic HEIF brands, which AVIF files use as well.
+const HEIF_BRANDS: [&[u8]; 2] = [b"mif1", b"msf1"];
+const AVIF_BRANDS: [&[u8]; 2] = [b"avif", b"avis"];
+
+/// Whether the `ftyp` box names HEIC: by its major brand, or a generic HEIF
+/// major brand whose compatible brands don't say AVIF.
+fn is_heic(bytes: &[u8]) -> bool {
+    if bytes.len() < 12 || &bytes[4..8] != b"ftyp" {
+        return false
+    }
+    let major = &bytes[8..12];
+    if HEIC_BRANDS.contains(&major) {
+        return true
+    }
+    if !HEIF_BRANDS.contains(&major) {
+        return false
+    }
+
+    // Compatible brands follow the minor version, up to the end of the box
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let end = size.min(bytes.len());
+    let compatible: Vec<&[u8]> = bytes.get(16..end).unwrap_or(&[]).chunks_exact(4).collect();
+    return !compatible.iter().any(|brand| AVIF_BRANDS.contains(brand))
+}
+
+#[cfg(feature = "heic")]
+fn decode_heic(bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    use image::RgbaImage;
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let heic_error = |e: libheif_rs::HeifError| {
+        ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("HEIC".to_string()), e))
+    };
+
+    let lib_heif = LibHeif::new();
+    let context = HeifContext::read_from_bytes(bytes).map_err(heic_error)?;
+    let handle = context.primary_image_handle().map_err(heic_error)?;
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None).map_err(heic_error)?;
+
+    let plane = image.planes().interleaved.ok_or_else(|| {
+        ImageError::Decoding(DecodingError::from_format_hint(ImageFormatHint::Name("HEIC".to_string())))
+    })?;
+    let row_len = plane.width as usize * 4;
+    let pixels: Vec<u8> = plane.data
+            .chunks(plane.stride)
+            .take(plane.height as usize)
+            .flat_map(|row| &row[..row_len])
+            .copied()
+            .collect();
+
+    return RgbaImage::from_raw(plane.width, plane.height, pixels)
+            .map(DynamicImage::ImageRgba8)
+            .ok_or_else(|| ImageError::Decoding(DecodingError::from_format_hint(ImageFormatHint::Name("HEIC".to_string()))))
+}
+
+#[cfg(not(feature = "heic"))]
+fn decode_heic(_bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    eprintln!("Error: HEIC images need pal to be built with `--features heic`");
+    exit(1);
+}
+
+/// Decodes a single image, turned upright according to its EXIF orientation.
+fn decode_still(reader: ImageReader<Cursor<&[u8]>>) -> Result<DynamicImage, ImageError> {
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    return Ok(img)
+}
+
+/// Decodes a still image as a single frame, or up to `max_frames` frames of
+/// an animated GIF, APNG or WebP.
+fn decode_frames(bytes: Vec<u8>, format_hint: Option<ImageFormat>, max_frames: usize) -> Result<Vec<DynamicImage>, ImageError> {
+    if is_heic(&bytes) {
+        return Ok(vec![decode_heic(&bytes)?])
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(bytes.as_slice())).with_guessed_format()?;
+    if reader.format().is_none()
+        && let Some(format) = format_hint {
+        reader.set_format(format);
+    }
+    if reader.format() == Some(ImageFormat::Avif) && !cfg!(feature = "avif") {
+        eprintln!("Error: AVIF images need pal to be built with `--features avif`");
+        exit(1);
+    }
+
+    let frames = match reader.format() {
+        Some(ImageFormat::Gif) if max_frames > 1 => {
+            spread_frames(GifDecoder::new(Cursor::new(bytes.as_slice()))?.into_frames(), max_frames)?
+        }
+        Some(ImageFormat::Png) if max_frames > 1 => {
+            let decoder = PngDecoder::new(Cursor::new(bytes.as_slice()))?;
+            if !decoder.is_apng()? { return Ok(vec![decode_still(reader)?]) }
+            spread_frames(decoder.apng()?.into_frames(), max_frames)?
+        }
+        Some(ImageFormat::WebP) if max_frames > 1 => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes.as_slice()))?;
+            if !decoder.has_animation() { return Ok(vec![decode_still(reader)?]) }
+            spread_frames(decoder.into_frames(), max_frames)?
+        }
+        _ => return Ok(vec![decode_still(reader)?]),
+    };
+
+    if frames.is_empty() { return Ok(vec![decode_still(reader)?]) }
+    return Ok(frames)
+}
+
+fn is_svg(image_uri: &str, bytes: &[u8]) -> bool {
+    let uri = image_uri.to_lowercase();
+    if uri.ends_with(".svg") || uri.ends_with(".svgz") {
+        return true
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+    return head.contains("<svg")
+}
+
+/// Renders an SVG (or gzipped SVGZ) with its longest side at most
+/// `SVG_MAX_SIDE`, plenty for sampling.
+#[cfg(feature = "svg")]
+fn rasterize_svg(bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    use image::RgbaImage;
+    use resvg::{tiny_skia, usvg};
+
+    const SVG_MAX_SIDE: f32 = 1024.0;
+
+    let svg_error = || ImageError::Decoding(DecodingError::from_format_hint(ImageFormatHint::Name("SVG".to_string())));
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_data(bytes, &options)
+            .map_err(|e| ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("SVG".to_string()), e)))?;
+
+    let size = tree.size();
+    let scale = (SVG_MAX_SIDE / size.width().max(size.height())).min(1.0);
+    let (width, height) = ((size.width() * scale).ceil() as u32, (size.height() * scale).ceil() as u32);
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(svg_error)?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia keeps premultiplied alpha
+    let pixels = pixmap.pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+    return RgbaImage::from_raw(width, height, pixels)
+            .map(DynamicImage::ImageRgba8)
+            .ok_or_else(svg_error)
+}
+
+#[cfg(not(feature = "svg"))]
+fn rasterize_svg(_bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    eprintln!("Error: SVG images need pal to be built with `--features svg`");
+    exit(1);
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        buffer = ((buffer << 6) | value as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    return Some(bytes)
+}
+
+fn decode_percent(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.bytes();
+    while let Some(c) = chars.next() {
+        if c == b'%'
+            && let Some(hex) = text.get(text.len() - chars.len()..text.len() - chars.len() + 2)
+            && let Ok(byte) = u8::from_str_radix(hex, 16) {
+            bytes.push(byte);
+            chars.nth(1);
+            continue;
+        }
+        bytes.push(c);
+    }
+    return bytes
+}
+
+/// Decodes `data:[<mediatype>][;base64],<data>`.
+fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    if header.ends_with(";base64") {
+        return decode_base64(data)
+    }
+    return Some(decode_percent(data))
+}
+
+/// Piped input may be a data uri or bare base64 rather than image bytes.
+fn decode_text_input(bytes: Vec<u8>) -> Vec<u8> {
+    if image::guess_format(&bytes).is_ok() {
+        return bytes
+    }
+    let Ok(text) = std::str::from_utf8(&bytes) else { return bytes };
+    let text = text.trim();
+    let decoded = if text.starts_with("data:") {
+        decode_data_uri(text)
+    } else {
+        decode_base64(text)
+    };
+    return decoded.filter(|d| !d.is_empty()).unwrap_or(bytes)
+}
+
+/// Camera RAW extensions; these files are TIFF-like containers with JPEG previews.
+const RAW_EXTENSIONS: [&str; 8] = ["cr2", "nef", "arw", "dng", "orf", "rw2", "raf", "pef"];
+
+fn is_raw(image_uri: &str) -> bool {
+    return Path::new(image_uri)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// The TIFF structure most RAW formats are built on: a byte order mark, then
+/// chained IFDs of 12 byte entries (tag, type, count, value or offset).
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+/// TIFF tags the preview search reads.
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_PHOTOMETRIC: u16 = 0x0106;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014a;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+/// Panasonic RW2's full size preview, stored as the tag's data.
+const TAG_RW2_JPEG: u16 = 0x002e;
+/// Canon CR2's raw IFD, which is lossless JPEG compressed.
+const TAG_CR2_SLICES: u16 = 0xc640;
+
+impl<'a> Tiff<'a> {
+    /// Panasonic (`IIU`) and Olympus (`IIRO`) change the magic number after
+    /// the byte order, so only the byte order is checked.
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let little_endian = match bytes.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _     => return None,
+        };
+        return Some(Tiff { bytes: bytes, little_endian: little_endian })
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let b: [u8; 2] = self.bytes.get(at..at.checked_add(2)?)?.try_into().ok()?;
+        return Some(if self.little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.bytes.get(at..at.checked_add(4)?)?.try_into().ok()?;
+        return Some(if self.little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    /// Positions of the entries of the IFD at `ifd`, and where the offset of
+    /// the next IFD is stored.
+    fn entries(&self, ifd: usize) -> Option<(Vec<usize>, usize)> {
+        let count = self.u16_at(ifd)? as usize;
+        let entries = (0..count).map(|i| ifd + 2 + i * 12).collect();
+        return Some((entries, ifd + 2 + count * 12))
+    }
+
+    fn find(&self, entries: &[usize], tag: u16) -> Option<usize> {
+        return entries.iter().copied().find(|&entry| self.u16_at(entry) == Some(tag))
+    }
+
+    /// The `i`-th SHORT or LONG value of an entry, inline or behind its offset.
+    fn value(&self, entry: usize, i: usize) -> Option<u32> {
+        let (size, count) = match self.u16_at(entry + 2)? {
+            3 => (2, self.u32_at(entry + 4)? as usize),
+            4 => (4, self.u32_at(entry + 4)? as usize),
+            _ => return None,
+        };
+        if i >= count {
+            return None
+        }
+        let data = if count.checked_mul(size)? <= 4 { entry + 8 } else { self.u32_at(entry + 8)? as usize };
+        let at = data.checked_add(i * size)?;
+        return if size == 2 { self.u16_at(at).map(u32::from) } else { self.u32_at(at) }
+    }
+
+    fn tag_value(&self, entries: &[usize], tag: u16) -> Option<u32> {
+        return self.value(self.find(entries, tag)?, 0)
+    }
+
+    fn orientation(&self) -> Option<Orientation> {
+        let (entries, _) = self.entries(self.u32_at(4)? as usize)?;
+        return Orientation::from_exif(self.tag_value(&entries, TAG_ORIENTATION)? as u8)
+    }
+
+    /// Offset and length of every JPEG the IFDs point to, following the IFD
+    /// chain and SubIFDs. Raw sensor data is passed over.
+    fn jpegs(&self) -> Vec<(usize, usize)> {
+        const MAX_IFDS: usize = 64;
+
+        let mut jpegs = Vec::new();
+        let mut visited = Vec::new();
+        let mut pending: Vec<usize> = self.u32_at(4).map(|ifd| ifd as usize).into_iter().collect();
+        while let Some(ifd) = pending.pop() {
+            if ifd == 0 || visited.contains(&ifd) || visited.len() == MAX_IFDS {
+                continue;
+            }
+            visited.push(ifd);
+            let Some((entries, next)) = self.entries(ifd) else { continue };
+
+            if let Some(next) = self.u32_at(next) {
+                pending.push(next as usize);
+            }
+            if let Some(sub_ifds) = self.find(&entries, TAG_SUB_IFDS) {
+                pending.extend((0..).map_while(|i| self.value(sub_ifds, i)).map(|ifd| ifd as usize));
+            }
+
+            // CFA (32803) and LinearRaw (34892) IFDs and CR2's sliced one hold
+            // sensor data, even when it's (lossless) JPEG compressed
+            let photometric = self.tag_value(&entries, TAG_PHOTOMETRIC);
+            if matches!(photometric, Some(32803 | 34892)) || self.find(&entries, TAG_CR2_SLICES).is_some() {
+                continue;
+            }
+
+            if let (Some(offset), Some(length)) = (self.tag_value(&entries, TAG_JPEG_OFFSET), self.tag_value(&entries, TAG_JPEG_LENGTH)) {
+                jpegs.push((offset as usize, length as usize));
+            }
+            // A JPEG as a single strip, as DNG previews and CR2's IFD0 are stored
+            let is_jpeg = matches!(self.tag_value(&entries, TAG_COMPRESSION), Some(6 | 7));
+            let single_strip = self.find(&entries, TAG_STRIP_OFFSETS).and_then(|e| self.u32_at(e + 4)) == Some(1);
+            if is_jpeg && single_strip
+                && let (Some(offset), Some(length)) = (self.tag_value(&entries, TAG_STRIP_OFFSETS), self.tag_value(&entries, TAG_STRIP_BYTE_COUNTS)) {
+                jpegs.push((offset as usize, length as usize));
+            }
+            if let Some(entry) = self.find(&entries, TAG_RW2_JPEG)
+                && let (Some(length), Some(offset)) = (self.u32_at(entry + 4), self.u32_at(entry + 8)) {
+                jpegs.push((offset as usize, length as usize));
+            }
+        }
+        return jpegs
+    }
+}
+
+/// Offset and length of the JPEGs embedded in a RAW file, from its IFDs or,
+/// for Fujifilm's RAF, its own header.
+fn raw_jpegs(bytes: &[u8]) -> Vec<(usize, usize)> {
+    if bytes.starts_with(b"FUJIFILMCCD-RAW") {
+        let be_u32_at = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?) as usize);
+        return be_u32_at(84).zip(be_u32_at(88)).into_iter().collect()
+    }
+    return Tiff::parse(bytes).map_or(Vec::new(), |tiff| tiff.jpegs())
+}
+
+/// Decodes the largest JPEG preview embedded in a RAW file. The previews are
+/// rendered by the camera, so they already have white balance and tone
+/// curves applied, which is what the sampler should see. Only the headers
+/// are read to rank them, a preview that then fails to decode gives way to
+/// the next largest.
+fn decode_raw_preview(bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    let mut previews: Vec<(&[u8], u64)> = raw_jpegs(bytes)
+            .into_iter()
+            .filter_map(|(offset, length)| {
+                let jpeg = bytes.get(offset..offset.checked_add(length)?)?;
+                if !jpeg.starts_with(&[0xff, 0xd8]) {
+                    return None
+                }
+                let (width, height) = ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg).into_dimensions().ok()?;
+                Some((jpeg, width as u64 * height as u64))
+            })
+            .collect();
+    previews.sort_by_key(|(_, area)| std::cmp::Reverse(*area));
+
+    // The camera records how it was held in the RAW itself; the preview's own EXIF is the fallback
+    let raw_orientation = Tiff::parse(bytes).and_then(|tiff| tiff.orientation());
+    for (jpeg, _) in previews {
+        let Ok(mut decoder) = JpegDecoder::new(Cursor::new(jpeg)) else { continue };
+        let orientation = raw_orientation
+                .or_else(|| decoder.orientation().ok())
+                .unwrap_or(Orientation::NoTransforms);
+        let Ok(mut img) = DynamicImage::from_decoder(decoder) else { continue };
+        img.apply_orientation(orientation);
+        return Ok(img)
+    }
+    return Err(ImageError::Decoding(DecodingError::from_format_hint(ImageFormatHint::Name("RAW".to_string()))))
+}
+
+/// Cuts every frame down to the crop region, clamped to the image bounds.
+fn crop_frames(frames: Vec<DynamicImage>, crop: Crop) -> Vec<DynamicImage> {
+    return frames
+            .into_iter()
+            .map(|img| {
+                let (width, height) = (img.width(), img.height());
+                let (x, y, w, h) = match crop {
+                    Crop::Region(x, y, w, h) => (x.min(width), y.min(height), w, h),
+                    Crop::Center(share) => {
+                        let w = (width as f32 * share) as u32;
+                        let h = (height as f32 * share) as u32;
+                        ((width - w) / 2, (height - h) / 2, w, h)
+                    }
+                };
+                let (w, h) = (w.min(width - x), h.min(height - y));
+                if w == 0 || h == 0 {
+                    eprintln!("Error: crop region lies outside the {}x{} image", width, height);
+                    exit(1);
+                }
+                img.crop_imm(x, y, w, h)
+            })
+            .collect()
+}
+
+pub fn read_image(image_uri: &str, image_bytes: Option<Vec<u8>>, conf: &Args, downloads_path: &Path) -> Result<Vec<DynamicImage>, ImageError> {
+    let (bytes, format_hint) = if let Some(bytes) = image_bytes {
+        (decode_text_input(bytes), None)
+    } else if image_uri.starts_with("data:") {
+        let bytes = decode_data_uri(image_uri).unwrap_or_else(|| {
+            eprintln!("Error: invalid data uri");
+            exit(1);
+        });
+        (bytes, None)
+    } else if image_uri.starts_with("http:") || image_uri.starts_with("https:") {
+        (get_cached_image_from_url(image_uri, conf, downloads_path), None)
+    } else {
+        (fs::read(image_uri).map_err(ImageError::IoError)?, ImageFormat::from_path(Path::new(image_uri)).ok())
+    };
+
+    let frames = if is_svg(image_uri, &bytes) {
+        vec![rasterize_svg(&bytes)?]
+    } else if is_raw(image_uri) {
+        vec![decode_raw_preview(&bytes)?]
+    } else {
+        decode_frames(bytes, format_hint, conf.frames)?
+    };
+    return match conf.crop {
+        Some(crop) => Ok(crop_frames(frames, crop)),
+        None       => Ok(frames),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 40, 40])))
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+                .unwrap();
+        return bytes
+    }
+
+    /// Lays out a TIFF-based RAW: data blobs first, then IFDs, children
+    /// before the IFDs pointing to them.
+    struct TiffWriter {
+        bytes: Vec<u8>,
+        little_endian: bool,
+    }
+
+    impl TiffWriter {
+        fn new(little_endian: bool) -> Self {
+            let header: &[u8] = if little_endian { b"II*\0\0\0\0\0" } else { b"MM\0*\0\0\0\0" };
+            return TiffWriter { bytes: header.to_vec(), little_endian: little_endian }
+        }
+
+        fn encode(&self, kind: u16, values: &[u32]) -> Vec<u8> {
+            return values.iter().flat_map(|&v| match (kind, self.little_endian) {
+                (3, true)  => (v as u16).to_le_bytes().to_vec(),
+                (3, false) => (v as u16).to_be_bytes().to_vec(),
+                (_, true)  => v.to_le_bytes().to_vec(),
+                (_, false) => v.to_be_bytes().to_vec(),
+            }).collect()
+        }
+
+        fn blob(&mut self, data: &[u8]) -> u32 {
+            let at = self.bytes.len() as u32;
+            self.bytes.extend(data);
+            return at
+        }
+
+        /// Entries are (tag, type, values), SHORT (3) or LONG (4).
+        fn ifd(&mut self, entries: &[(u16, u16, &[u32])], next: u32) -> u32 {
+            let fields: Vec<Vec<u8>> = entries.iter().map(|(_, kind, values)| {
+                let data = self.encode(*kind, values);
+                if data.len() <= 4 {
+                    return [data, vec![0; 4]].concat()[..4].to_vec()
+                }
+                let at = self.blob(&data);
+                return self.encode(4, &[at])
+            }).collect();
+
+            let at = self.blob(&self.encode(3, &[entries.len() as u32]));
+            for ((tag, kind, values), field) in entries.iter().zip(fields) {
+                let entry = [self.encode(3, &[*tag as u32, *kind as u32]), self.encode(4, &[values.len() as u32]), field].concat();
+                self.blob(&entry);
+            }
+            self.blob(&self.encode(4, &[next]));
+            return at
+        }
+
+        fn finish(mut self, ifd0: u32) -> Vec<u8> {
+            let ifd0 = self.encode(4, &[ifd0]);
+            self.bytes[4..8].copy_from_slice(&ifd0);
+            return self.bytes
+        }
+    }
+
+    #[test]
+    fn dng_preview_from_sub_ifds() {
+        let mut dng = TiffWriter::new(true);
+        let thumbnail = dng.blob(&[90; 16 * 8 * 3]);
+        // Lossless JPEG sensor data, stood in for by a bigger regular one
+        let sensor = jpeg(80, 60);
+        let sensor_at = dng.blob(&sensor);
+        let preview = jpeg(40, 20);
+        let preview_at = dng.blob(&preview);
+
+        let raw_ifd = dng.ifd(&[
+            (0x00fe, 4, &[0]),
+            (0x0103, 3, &[7]),
+            (0x0106, 3, &[32803]),
+            (0x0111, 4, &[sensor_at]),
+            (0x0117, 4, &[sensor.len() as u32]),
+        ], 0);
+        let preview_ifd = dng.ifd(&[
+            (0x00fe, 4, &[1]),
+            (0x0103, 3, &[7]),
+            (0x0106, 3, &[6]),
+            (0x0111, 4, &[preview_at]),
+            (0x0117, 4, &[preview.len() as u32]),
+        ], 0);
+        let ifd0 = dng.ifd(&[
+            (0x00fe, 4, &[1]),
+            (0x0103, 3, &[1]),
+            (0x0106, 3, &[2]),
+            (0x0111, 4, &[thumbnail]),
+            (0x0112, 3, &[6]),
+            (0x014a, 4, &[raw_ifd, preview_ifd]),
+        ], 0);
+
+        // Orientation 6 turns the 40x20 preview upright
+        let img = decode_raw_preview(&dng.finish(ifd0)).unwrap();
+        assert_eq!((img.width(), img.height()), (20, 40));
+    }
+
+    #[test]
+    fn cr2_preview_from_ifd0() {
+        let mut cr2 = TiffWriter::new(true);
+        let full = jpeg(60, 40);
+        let full_at = cr2.blob(&full);
+        let thumbnail = jpeg(16, 10);
+        let thumbnail_at = cr2.blob(&thumbnail);
+        let small_rgb = cr2.blob(&[90; 8 * 4 * 3]);
+        // Lossless JPEG sensor data, stood in for by a bigger regular one
+        let sensor = jpeg(100, 100);
+        let sensor_at = cr2.blob(&sensor);
+
+        let ifd3 = cr2.ifd(&[
+            (0x0103, 3, &[6]),
+            (0x0111, 4, &[sensor_at]),
+            (0x0117, 4, &[sensor.len() as u32]),
+            (0xc640, 3, &[1, 50, 50]),
+        ], 0);
+        let ifd2 = cr2.ifd(&[
+            (0x0103, 3, &[1]),
+            (0x0111, 4, &[small_rgb]),
+        ], ifd3);
+        let ifd1 = cr2.ifd(&[
+            (0x0103, 3, &[6]),
+            (0x0201, 4, &[thumbnail_at]),
+            (0x0202, 4, &[thumbnail.len() as u32]),
+        ], ifd2);
+        let ifd0 = cr2.ifd(&[
+            (0x0103, 3, &[6]),
+            (0x0111, 4, &[full_at]),
+            (0x0112, 3, &[1]),
+            (0x0117, 4, &[full.len() as u32]),
+        ], ifd1);
+
+        let img = decode_raw_preview(&cr2.finish(ifd0)).unwrap();
+        assert_eq!((img.width(), img.height()), (60, 40));
+    }
+}
@@ -5,63 +5,114 @@ use std::fmt::Write;
 use std::path::{Path, PathBuf};
 use std::hash::{Hash, Hasher};
 use std::process::exit;
-use std::time::UNIX_EPOCH;
-use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
 use image::Rgba;
-use image::{ImageReader, ImageError, DynamicImage};
+use image::DynamicImage;
 
 mod colorscheme;
 mod cli;
 mod template;
-use crate::colorscheme::{Color, Colorscheme, aaverage_generate_colorscheme, kmeans_generate_colorscheme, ansi_generate_colorscheme};
-use crate::cli::{Args, Method, OutputFormat};
-use crate::template::process_template_files;
+mod input;
+mod import;
+mod exporters;
+use crate::colorscheme::{Color, Colorscheme, aaverage_generate_colorscheme, kmeans_generate_colorscheme, ansi_generate_colorscheme, material_generate_colorscheme, vibrant_generate_colorscheme, seed_generate_colorscheme, is_light, is_monochrome, monochrome_generate_colorscheme, sample_grid, sample_grid_pixels, sample_full, pool_weighted_samples, SampleFilter};
+use crate::cli::{Args, Method, Sampling, Theme, Bright, Crop, Import, Subcommand, OutputFormat, Export};
+use crate::template::{process_template_files, render_template, format_color, undeploy, lint};
+use crate::import::import_scheme;
+use crate::exporters::{render_exports, reload_changed, exporter};
+use crate::input::{read_image, read_stdin_bytes, capture_screen};
 
-fn hash_image_uri(image_uri: &str, saturation: &f32, method: &Method, colorschemes_cache_path: &Path) -> PathBuf {
+fn hash_images(images: &[(String, f32)], image_bytes: &[Option<Vec<u8>>], conf: &Args, colorschemes_cache_path: &Path) -> PathBuf {
     let mut hasher = DefaultHasher::new();
-    image_uri.hash(&mut hasher);
+    for ((image_uri, weight), bytes) in images.iter().zip(image_bytes) {
+        image_uri.hash(&mut hasher);
+        weight.to_bits().hash(&mut hasher);
+        bytes.hash(&mut hasher);
 
-    if let Ok(meta) = fs::metadata(image_uri) {
-        if let Ok(mtime) = meta.modified() {
+        if let Ok(meta) = fs::metadata(image_uri)
+            && let Ok(mtime) = meta.modified() {
             mtime.duration_since(UNIX_EPOCH).unwrap().as_secs().hash(&mut hasher);
         }
     }
 
-    saturation.to_bits().hash(&mut hasher);
-    match method {
-        Method::AreaAverage => 0u8.hash(&mut hasher),
-        Method::KMeans      => 1u8.hash(&mut hasher),
-        Method::ANSI        => 2u8.hash(&mut hasher),
+    conf.saturation.to_bits().hash(&mut hasher);
+    conf.brightness.to_bits().hash(&mut hasher);
+    conf.contrast.to_bits().hash(&mut hasher);
+    conf.temperature.to_bits().hash(&mut hasher);
+    conf.method.hash(&mut hasher);
+    conf.theme.hash(&mut hasher);
+    conf.bright.hash(&mut hasher);
+    conf.pastel.hash(&mut hasher);
+    conf.duotone.hash(&mut hasher);
+    conf.harmony.hash(&mut hasher);
+    conf.cvd.hash(&mut hasher);
+    conf.high_contrast.hash(&mut hasher);
+    conf.ramps.hash(&mut hasher);
+    conf.background.map(|c| [c.r, c.g, c.b]).hash(&mut hasher);
+    conf.foreground.map(|c| [c.r, c.g, c.b]).hash(&mut hasher);
+    conf.seed.map(|c| [c.r, c.g, c.b]).hash(&mut hasher);
+    conf.import.hash(&mut hasher);
+    conf.sampling.hash(&mut hasher);
+    conf.frames.hash(&mut hasher);
+    match conf.crop {
+        Some(Crop::Region(x, y, w, h)) => [x, y, w, h].hash(&mut hasher),
+        Some(Crop::Center(share))      => share.to_bits().hash(&mut hasher),
+        None                           => 0.hash(&mut hasher),
     }
+    conf.min_chroma.hash(&mut hasher);
+    conf.distinctness.hash(&mut hasher);
+    for (from, to) in conf.exclude_hues.iter().chain(&conf.only_hues) {
+        from.to_bits().hash(&mut hasher);
+        to.to_bits().hash(&mut hasher);
+    }
+    conf.exclude_hues.len().hash(&mut hasher);
 
     let cache_file_name = format!("{:x}.pal", hasher.finish());
     let cache_file_path = colorschemes_cache_path.join(cache_file_name);
     return cache_file_path
 }
 
-fn read_scheme_cache(cache_file_path: &Path) -> Colorscheme {
-    let content = fs::read_to_string(cache_file_path).unwrap_or_else(|e| {
-        eprintln!("Error: could not read cache file: {}", e);
+/// Reads a scheme in the cache format: background, foreground, palette
+/// colors, then `name #rrggbb` lines. Hand-written palette files use the same
+/// format and may contain `//` comments.
+fn read_scheme_file(scheme_file_path: &Path) -> Colorscheme {
+    let content = fs::read_to_string(scheme_file_path).unwrap_or_else(|e| {
+        eprintln!("Error: could not read '{}': {}", scheme_file_path.display(), e);
         exit(1);
     });
 
     let mut lines = content.lines()
-                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| l.split("//").next().unwrap_or("").trim())
+                    .filter(|l| !l.is_empty())
                     .map(|l| l.trim_start_matches('#').trim());
 
     let background: Color = parse_hex_line(lines.next().unwrap_or_else(|| {
-        eprintln!("Error: missing background color in cache");
+        eprintln!("Error: missing background color in '{}'", scheme_file_path.display());
         exit(1);
     }));
     let foreground: Color = parse_hex_line(lines.next().unwrap_or_else(|| {
-        eprintln!("Error: missing foreground color in cache");
+        eprintln!("Error: missing foreground color in '{}'", scheme_file_path.display());
         exit(1);
     }));
-    let palette: Vec<Color> = lines.map(parse_hex_line).collect();
+    let mut palette: Vec<Color> = Vec::new();
+    let mut named: Vec<(String, Color)> = Vec::new();
+    let mut monochrome = false;
+    for line in lines {
+        if line == "monochrome" {
+            monochrome = true;
+            continue;
+        }
+        match line.split_once(' ') {
+            Some((name, hex)) => named.push((name.to_string(), parse_hex_line(hex.trim().trim_start_matches('#')))),
+            None => palette.push(parse_hex_line(line)),
+        }
+    }
 
     return Colorscheme { palette:    palette, 
                          background: background, 
-                         foreground: foreground }
+                         foreground: foreground,
+                         named:      named,
+                         monochrome: monochrome }
 }
 
 fn parse_hex_line(s: &str) -> Color {
@@ -85,32 +136,6 @@ fn parse_hex_line(s: &str) -> Color {
     return Color::from_rgba(Rgba([r, g, b, 255]))
 }
 
-fn get_image_from_url(url: &str) -> Result<Vec<u8>, attohttpc::Error> {
-    let response = attohttpc::get(url).send().map_err(|_| {
-        eprintln!("Error");
-        exit(1);
-    });
-    let data = response.expect("Failed to get image from url").bytes()?;
-    Ok(data)
-}
-
-fn read_image(image_uri: &str) -> Result<DynamicImage, ImageError> {
-    if image_uri.starts_with("http:") || image_uri.starts_with("https:") {
-        let bytes = get_image_from_url(image_uri).map_err(|_| {
-                eprintln!("Error");
-                exit(1);
-            }
-        );
-        let img = ImageReader::new(Cursor::new(bytes.unwrap()))
-            .with_guessed_format()?
-            .decode()?;
-        return Ok(img)
-    } else {
-        let img = ImageReader::open(image_uri)?.decode()?;
-        return Ok(img)
-    }
-}
-
 fn write_scheme_cache(cache_file_path: &Path, colorscheme: &Colorscheme) -> Result<(), ()> {
     let mut content = String::new();
     writeln!(content, "#{:02x}{:02x}{:02x}", colorscheme.background.r, colorscheme.background.g, colorscheme.background.b).ok();
@@ -118,6 +143,12 @@ fn write_scheme_cache(cache_file_path: &Path, colorscheme: &Colorscheme) -> Resu
     for c in &colorscheme.palette {
         writeln!(content, "#{:02x}{:02x}{:02x}", c.r, c.g, c.b).ok();
     }
+    for (name, c) in &colorscheme.named {
+        writeln!(content, "{} #{:02x}{:02x}{:02x}", name, c.r, c.g, c.b).ok();
+    }
+    if colorscheme.monochrome {
+        writeln!(content, "monochrome").ok();
+    }
 
     fs::write(cache_file_path, content).map_err(|_| {
         eprintln!("Error: could not cache colorscheme");
@@ -125,57 +156,325 @@ fn write_scheme_cache(cache_file_path: &Path, colorscheme: &Colorscheme) -> Resu
     })
 }
 
-fn handle_paths() -> (PathBuf, PathBuf, PathBuf) {
+fn sample_frames(frames: &[DynamicImage], conf: &Args) -> Vec<Color> {
+    return frames
+            .iter()
+            .flat_map(|img| match (conf.sampling, conf.method) {
+                (Sampling::Full, _)              => sample_full(img),
+                (Sampling::Grid, Method::KMeans) => sample_grid_pixels(img),
+                (Sampling::Grid, _)              => sample_grid(img),
+            })
+            .collect()
+}
+
+fn generate_colorscheme(sources: &[(Vec<DynamicImage>, f32)], conf: &Args) -> Colorscheme {
+    let sample_sets: Vec<(Vec<Color>, f32)> = sources
+            .iter()
+            .map(|(frames, weight)| (sample_frames(frames, conf), *weight))
+            .collect();
+    let samples = pool_weighted_samples(&sample_sets);
+
+    let light = match conf.theme {
+        Theme::Dark  => false,
+        Theme::Light => true,
+        Theme::Auto  => is_light(&samples),
+    };
+
+    let filter = SampleFilter { min_chroma:   conf.min_chroma,
+                                exclude_hues: conf.exclude_hues.clone(),
+                                only_hues:    conf.only_hues.clone() };
+
+    let monochrome = is_monochrome(&samples);
+
+    let colorscheme = match conf.method {
+        Method::AreaAverage | Method::KMeans | Method::ANSI if monochrome => monochrome_generate_colorscheme(&samples),
+        Method::AreaAverage => aaverage_generate_colorscheme(&samples, &filter, conf.distinctness),
+        Method::KMeans      => kmeans_generate_colorscheme(&samples, &filter),
+        Method::ANSI        => ansi_generate_colorscheme(&samples, &filter),
+        Method::Material    => material_generate_colorscheme(&samples, &filter),
+        Method::Vibrant     => vibrant_generate_colorscheme(&samples, &filter),
+    }.with_accents(&samples)
+     .with_theme(light);
+
+    return adjust_colorscheme(colorscheme, conf)
+}
+
+/// WCAG AA contrast for normal text, what --high-contrast guarantees.
+const HIGH_CONTRAST_RATIO: f32 = 4.5;
+
+/// Applies the adjustment flags to an extracted or hand-written scheme and
+/// derives the named roles.
+fn adjust_colorscheme(colorscheme: Colorscheme, conf: &Args) -> Colorscheme {
+    let mut colorscheme = colorscheme
+            .with_saturation(conf.saturation)
+            .with_brightness(conf.brightness)
+            .with_temperature(conf.temperature);
+
+    if let Some(bg) = conf.background {
+        colorscheme.background = bg;
+    }
+    if let Some(fg) = conf.foreground {
+        colorscheme.foreground = fg;
+    }
+    colorscheme = colorscheme.with_contrast(conf.contrast);
+
+    if let Some(harmony) = conf.harmony {
+        colorscheme = colorscheme.with_harmony(harmony);
+    }
+    if conf.duotone {
+        colorscheme = colorscheme.with_duotone();
+    }
+    if let Bright::Derived = conf.bright {
+        colorscheme = colorscheme.with_derived_brights();
+    }
+    if conf.pastel {
+        colorscheme = colorscheme.with_pastel();
+    }
+    if let Some(cvd) = conf.cvd {
+        colorscheme = colorscheme.with_cvd_safety(cvd);
+    }
+    if conf.ramps > 0 {
+        colorscheme = colorscheme.with_ramps(conf.ramps);
+    }
+    colorscheme = colorscheme
+            .with_semantic_roles()
+            .with_surfaces()
+            .with_color_names(matches!(conf.method, Method::ANSI));
+
+    if conf.high_contrast {
+        colorscheme = colorscheme.with_min_contrast(HIGH_CONTRAST_RATIO);
+        // A foreground given with --fg is kept as it is
+        if let Some(fg) = conf.foreground {
+            if fg.contrast_ratio(&colorscheme.background) < HIGH_CONTRAST_RATIO {
+                eprintln!("Warning: --fg {} is below {}:1 contrast, keeping it anyway", format_color(&fg, None, OutputFormat::HEX), HIGH_CONTRAST_RATIO);
+            }
+            colorscheme.foreground = fg;
+        }
+    }
+    return colorscheme
+}
+
+fn is_palette_file(image_uri: &str) -> bool {
+    return Path::new(image_uri)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pal") || ext.eq_ignore_ascii_case("txt"))
+}
+
+/// Absolute path or url of the first input image, for `@wallpaper`.
+fn wallpaper_path(images: &[(String, f32)], conf: &Args) -> Option<String> {
+    let (image_uri, _) = images.first()?;
+    if conf.screen || conf.seed.is_some() || image_uri == "-" || image_uri.starts_with("data:") {
+        return None
+    }
+    if image_uri.starts_with("http:") || image_uri.starts_with("https:") {
+        return Some(image_uri.clone())
+    }
+    return fs::canonicalize(image_uri).ok().map(|p| p.display().to_string())
+}
+
+/// Formats unix seconds as an ISO 8601 UTC timestamp.
+fn format_timestamp(secs: u64) -> String {
+    // Howard Hinnant's days-to-civil conversion
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let time = secs % 86400;
+    return format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+/// Provenance placeholders: `@method`, `@saturation`, `@generated_at` and
+/// `@checksum`, the hash the scheme is cached under.
+fn metadata_variables(images: &[(String, f32)], conf: &Args, cache_file_path: &Path) -> Vec<(String, String)> {
+    let method = if conf.seed.is_some() {
+        "seed"
+    } else if let Some(import) = conf.import {
+        match import {
+            Import::Pywal  => "pywal",
+            Import::Base16 => "base16",
+        }
+    } else if let [(image_uri, _)] = images && is_palette_file(image_uri) {
+        "palette"
+    } else {
+        match conf.method {
+            Method::AreaAverage => "area_average",
+            Method::KMeans      => "kmeans",
+            Method::ANSI        => "ansi",
+            Method::Material    => "material",
+            Method::Vibrant     => "vibrant",
+        }
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let checksum = cache_file_path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+
+    return vec![
+        ("method".to_string(),       method.to_string()),
+        ("saturation".to_string(),   conf.saturation.to_string()),
+        ("generated_at".to_string(), format_timestamp(now)),
+        ("checksum".to_string(),     checksum),
+    ]
+}
+
+/// The scheme cache entry written or used last, which is the current scheme.
+fn latest_scheme_cache(colorschemes_cache_path: &Path) -> Option<PathBuf> {
+    return fs::read_dir(colorschemes_cache_path).ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .filter(|(_, path)| path.is_file())
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, path)| path)
+}
+
+/// With `dry_run` the directories are only named, not created.
+fn handle_paths(dry_run: bool) -> (PathBuf, PathBuf, PathBuf, PathBuf) {
     let home = env::var("HOME").expect("HOME env not set");
     let config_path = Path::new(&home).join(".config/pal");
     let templates_cache_path = Path::new(&home).join(".cache/pal");
     let colorschemes_cache_path = Path::new(&home).join(".cache/pal/other");
+    let downloads_cache_path = Path::new(&home).join(".cache/pal/downloads");
+    if dry_run {
+        return (config_path, templates_cache_path, colorschemes_cache_path, downloads_cache_path)
+    }
     fs::create_dir_all(&config_path).expect("failed to create config dir");
     fs::create_dir_all(&templates_cache_path).expect("failed to create templates cache dir");
     fs::create_dir_all(&colorschemes_cache_path).expect("failed to create colorschemes cache dir");
-    return (config_path, templates_cache_path, colorschemes_cache_path)
+    fs::create_dir_all(&downloads_cache_path).expect("failed to create downloads cache dir");
+    return (config_path, templates_cache_path, colorschemes_cache_path, downloads_cache_path)
 }
 
 fn main() -> Result<(), ()> {
-    let (conf, image_uri) = Args::from_cli();
-    let (config_path, templates_cache_path, colorschemes_cache_path) = handle_paths();
-    let hashed_image_uri = hash_image_uri(&image_uri, &conf.saturation, &conf.method, &colorschemes_cache_path);
+    let (conf, images) = Args::from_cli();
+    let (config_path, templates_cache_path, colorschemes_cache_path, downloads_cache_path) = handle_paths(conf.dry_run);
+    if conf.subcommand == Subcommand::Undeploy {
+        return undeploy(&templates_cache_path).map_err(|e| {
+            eprintln!("Error: could not undeploy templates; '{}'", e);
+            exit(1)
+        })
+    }
+    if conf.subcommand == Subcommand::Lint {
+        let problems = lint(&config_path).map_err(|e| {
+            eprintln!("Error: could not read template files; '{}'", e);
+            exit(1)
+        })?;
+        if problems > 0 {
+            exit(1);
+        }
+        return Ok(())
+    }
+    let image_bytes: Vec<Option<Vec<u8>>> = images.iter().map(|(image_uri, _)| {
+        if conf.screen {
+            Some(capture_screen())
+        } else if image_uri == "-" {
+            Some(read_stdin_bytes())
+        } else {
+            None
+        }
+    }).collect();
+    let hashed_image_uri = if images.is_empty() {
+        // `pal render` without an image uses the current scheme
+        latest_scheme_cache(&colorschemes_cache_path).unwrap_or_else(|| {
+            eprintln!("Error: no colorscheme has been generated yet, pass an image to render with");
+            exit(1);
+        })
+    } else {
+        hash_images(&images, &image_bytes, &conf, &colorschemes_cache_path)
+    };
     let colorscheme: Colorscheme;
 
     if hashed_image_uri.exists() {
-        colorscheme = read_scheme_cache(&hashed_image_uri);
-    } else {
-        let img = read_image(&image_uri).map_err(|_| {
-            eprintln!("Error: could not find image '{}'", image_uri);
-            exit(1)
-        })?;
-        
-        colorscheme = match conf.method {
-            Method::AreaAverage => aaverage_generate_colorscheme(&img).with_saturation(conf.saturation),
-            Method::KMeans      => kmeans_generate_colorscheme(&img).with_saturation(conf.saturation),
-            Method::ANSI        => ansi_generate_colorscheme(&img).with_saturation(conf.saturation),
+        colorscheme = read_scheme_file(&hashed_image_uri);
+        // Marks it as the current scheme again
+        if !conf.dry_run && conf.subcommand != Subcommand::Render {
+            let _ = fs::File::options().write(true).open(&hashed_image_uri).and_then(|f| f.set_modified(SystemTime::now()));
+        }
+    } else if let Some(seed) = conf.seed {
+        let light = matches!(conf.theme, Theme::Light);
+        colorscheme = adjust_colorscheme(seed_generate_colorscheme(seed).with_theme(light), &conf);
+    } else if let [(image_uri, _)] = images.as_slice()
+        && (conf.import.is_some() || is_palette_file(image_uri)) {
+        let scheme = match conf.import {
+            Some(kind) => import_scheme(kind, Path::new(image_uri)),
+            None       => read_scheme_file(Path::new(image_uri)),
         };
+        let palette = scheme.palette.clone();
+        colorscheme = adjust_colorscheme(scheme.with_accents(&palette), &conf);
+    } else {
+        let mut sources = Vec::new();
+        for ((image_uri, weight), bytes) in images.iter().zip(image_bytes) {
+            let frames = read_image(image_uri, bytes, &conf, &downloads_cache_path).map_err(|_| {
+                eprintln!("Error: could not find image '{}'", image_uri);
+                exit(1)
+            })?;
+            sources.push((frames, *weight));
+        }
 
+        colorscheme = generate_colorscheme(&sources, &conf);
+    }
+
+    // Rendering a single template leaves the current scheme as it is
+    if !hashed_image_uri.exists() && !conf.dry_run && conf.subcommand != Subcommand::Render {
         let _ = write_scheme_cache(&hashed_image_uri, &colorscheme).map_err(|_| {
             eprint!("Warning: failed to cache colorscheme");
         });
     }
-    
+
+    let mut variables = metadata_variables(&images, &conf, &hashed_image_uri);
+    if let Some(wallpaper) = wallpaper_path(&images, &conf) {
+        variables.push(("wallpaper".to_string(), wallpaper));
+    }
+
+    if let Some(template) = &conf.template {
+        let content = if template == "-" {
+            String::from_utf8(read_stdin_bytes()).map_err(|_| {
+                eprintln!("Error: template is not valid UTF-8");
+                exit(1)
+            })?
+        } else {
+            fs::read_to_string(template).map_err(|e| {
+                eprintln!("Error: could not read template '{}'; '{}'", template, e);
+                exit(1)
+            })?
+        };
+        let rendered = render_template(&content, &config_path, &colorscheme, &variables, conf.format).map_err(|e| {
+            eprintln!("Error: could not render template; '{}'", e);
+            exit(1)
+        })?;
+        print!("{}", rendered);
+        return Ok(())
+    }
+
     if !conf.preview {
-        let _ = process_template_files(config_path, templates_cache_path, &colorscheme, conf.format).map_err(|e| {
+        let exports = render_exports(&conf.exports, &colorscheme, &variables);
+        let changed = process_template_files(config_path, templates_cache_path.clone(), &colorscheme, &variables, &conf, &exports).map_err(|e| {
             eprintln!("Error: could not process template files; '{}'", e);
             exit(1)
-        });
+        })?;
+        reload_changed(&conf.exports, &changed, &templates_cache_path);
     }
 
     if conf.verbose {
+        if colorscheme.monochrome {
+            eprintln!("Note: image is monochrome, palette colors were synthesized");
+        }
         match conf.format {
+            OutputFormat::JSON => {
+                print!("{}", String::from_utf8_lossy(&exporter(Export::Json).render(&colorscheme, &variables)));
+            }
             OutputFormat::HEX => {
                 println!("#{:02x}{:02x}{:02x}", &colorscheme.background.r, &colorscheme.background.g, &colorscheme.background.b);
                 println!("#{:02x}{:02x}{:02x}", &colorscheme.foreground.r, &colorscheme.foreground.g, &colorscheme.foreground.b);
                 for c in &colorscheme.palette {
                     println!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b);
                 }
+                for (name, c) in &colorscheme.named {
+                    println!("{} #{:02x}{:02x}{:02x}", name, c.r, c.g, c.b);
+                }
             }
             OutputFormat::RGB => {
                 println!("rgb({}, {}, {})", &colorscheme.background.r, &colorscheme.background.g, &colorscheme.background.b);
@@ -183,9 +482,81 @@ fn main() -> Result<(), ()> {
                 for c in &colorscheme.palette {
                     println!("rgb({}, {}, {})", c.r, c.g, c.b);
                 }
+                for (name, c) in &colorscheme.named {
+                    println!("{} rgb({}, {}, {})", name, c.r, c.g, c.b);
+                }
+            }
+            format => {
+                println!("{}", format_color(&colorscheme.background, None, format));
+                println!("{}", format_color(&colorscheme.foreground, None, format));
+                for c in &colorscheme.palette {
+                    println!("{}", format_color(c, None, format));
+                }
+                for (name, c) in &colorscheme.named {
+                    println!("{} {}", name, format_color(c, None, format));
+                }
             }
         }
     }
     
     return Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Color {
+        return Color::from_hex(s).unwrap()
+    }
+
+    /// A dark scheme whose colors all sit close to the background.
+    fn dim_scheme() -> Colorscheme {
+        let palette = ["#2a1a1a", "#3a2020", "#203a20", "#3a3a20", "#20203a", "#3a203a", "#203a3a", "#303030"];
+        return Colorscheme {
+            palette:    palette.iter().map(|c| hex(c)).collect(),
+            background: hex("#1a1a1a"),
+            foreground: hex("#333333"),
+            named:      Vec::new(),
+            monochrome: false,
+        }
+    }
+
+    #[test]
+    fn high_contrast_covers_every_role() {
+        let conf = Args { high_contrast: true, ramps: 2, ..Args::default() };
+        let colorscheme = adjust_colorscheme(dim_scheme(), &conf);
+        let bg = colorscheme.background;
+
+        assert!(colorscheme.foreground.contrast_ratio(&bg) >= HIGH_CONTRAST_RATIO);
+        for c in &colorscheme.palette {
+            assert!(c.contrast_ratio(&bg) >= HIGH_CONTRAST_RATIO);
+        }
+        for name in ["error", "warning", "success", "info", "black", "red", "white", "overlay0", "subtext1", "color3_d2"] {
+            assert!(colorscheme.named.iter().any(|(n, _)| n == name), "missing {}", name);
+        }
+        for (name, c) in &colorscheme.named {
+            if ["crust", "mantle", "surface0", "surface1", "surface2"].contains(&name.as_str()) {
+                continue;
+            }
+            assert!(c.contrast_ratio(&bg) >= HIGH_CONTRAST_RATIO, "{} is {:.2}:1", name, c.contrast_ratio(&bg));
+        }
+    }
+
+    #[test]
+    fn seed_is_the_accent() {
+        let seed = hex("#7aa2f7");
+        let colorscheme = adjust_colorscheme(seed_generate_colorscheme(seed).with_theme(false), &Args::default());
+        let named = |name: &str| colorscheme.named.iter().find(|(n, _)| n == name).map(|(_, c)| format_color(c, None, OutputFormat::HEX));
+        assert_eq!(named("accent").as_deref(), Some("#7aa2f7"));
+        assert!(named("accent2").is_some_and(|c| c != "#7aa2f7"));
+    }
+
+    #[test]
+    fn high_contrast_keeps_explicit_foreground() {
+        let fg = hex("#222222");
+        let conf = Args { high_contrast: true, foreground: Some(fg), ..Args::default() };
+        let colorscheme = adjust_colorscheme(dim_scheme(), &conf);
+        assert_eq!(format_color(&colorscheme.foreground, None, OutputFormat::HEX), "#222222");
+    }
+}
@@ -1,29 +1,347 @@
 use std::fs;
-use std::path::PathBuf;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 use crate::colorscheme::{Color, Colorscheme};
-use crate::cli::OutputFormat;
+use crate::cli::{Args, Deploy, OutputFormat};
 
-pub fn process_template_files(config_path: PathBuf, cache_path: PathBuf, colorscheme: &Colorscheme, format: OutputFormat) -> Result<(), std::io::Error> {
-    for entry in fs::read_dir(config_path)? {
-        let entry = entry?;
-        let path = entry.path();
+/// Maps template paths, relative to the config dir, to extra destinations.
+const TARGETS_FILE: &str = "targets.toml";
+/// User defined `key = "value"` pairs, available as `@var.key`.
+const VARIABLES_FILE: &str = "variables.toml";
+/// Templates in this subdirectory use pywal's `{color0}` syntax.
+const PYWAL_DIR: &str = "pywal";
+/// Destinations put in place by `--deploy`, one per line, kept in the cache dir.
+const DEPLOY_MANIFEST: &str = ".deployed";
 
-        if !path.is_file() {
-            continue;
+struct Renderer<'a> {
+    colorscheme: &'a Colorscheme,
+    variables: &'a [(String, String)],
+    format: OutputFormat,
+    targets: Vec<(PathBuf, PathBuf)>,
+    deploy: Option<Deploy>,
+    manifest_path: PathBuf,
+    deployed: Vec<PathBuf>,
+    dry_run: bool,
+    diff: bool,
+}
+
+impl Renderer<'_> {
+    fn render_dir(&mut self, dir: &Path, out_dir: &Path, relative: &Path) -> Result<(), std::io::Error> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let out_path = out_dir.join(entry.file_name());
+            let relative = relative.join(entry.file_name());
+
+            if path.is_dir() {
+                if !self.dry_run {
+                    fs::create_dir_all(&out_path)?;
+                }
+                self.render_dir(&path, &out_path, &relative)?;
+                continue;
+            }
+            if !path.is_file() || relative == Path::new(TARGETS_FILE) || relative == Path::new(VARIABLES_FILE) {
+                continue;
+            }
+
+            let (out_path, content) = match path.extension() {
+                Some(extension) if extension == "tera" => {
+                    // A broken Tera template is skipped, the others still render
+                    match render_tera(&fs::read_to_string(&path)?, self.colorscheme, self.variables, self.format) {
+                        Ok(content) => (out_path.with_extension(""), content),
+                        Err(e) => {
+                            eprintln!("Error: could not render '{}'; '{}'", relative.display(), e);
+                            continue;
+                        }
+                    }
+                }
+                _ if relative.starts_with(PYWAL_DIR) => {
+                    (out_path, render_pywal(&fs::read_to_string(&path)?, self.colorscheme, self.variables))
+                }
+                _ => (out_path, parse_template(path.clone(), self.colorscheme, self.variables, self.format)?),
+            };
+            self.write_output(Some(&path), &out_path, &relative, content.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes a rendered template or export and its `targets.toml`
+    /// destination, returning whether the output changed. `template` is the
+    /// file whose permissions and mtime the output takes, if any.
+    fn write_output(&mut self, template: Option<&Path>, out_path: &Path, relative: &Path, content: &[u8]) -> Result<bool, std::io::Error> {
+        let changed = !is_up_to_date(out_path, content);
+        if self.dry_run {
+            self.report(out_path, content)?;
+        }
+        // Rewriting an unchanged file would make programs watching it reload
+        else if changed {
+            // A read-only output from the last run can't be written over
+            if fs::metadata(out_path).is_ok_and(|m| m.permissions().readonly()) {
+                fs::remove_file(out_path)?;
+            }
+            fs::write(out_path, content)?;
+            if let Some(template) = template {
+                copy_metadata(template, out_path)?;
+            }
         }
 
-        let content = parse_template(path.clone(), colorscheme, format)?;
+        if let Some((_, destination)) = self.targets.iter().find(|(template, _)| *template == relative) {
+            let destination = destination.clone();
+            if self.dry_run {
+                self.report(&destination, content)?;
+            } else {
+                self.write_target(out_path, &destination, content)?;
+            }
+        }
+        Ok(changed && !self.dry_run)
+    }
+
+    /// Prints what writing `content` to `path` would do and, with `--diff`,
+    /// the changes it would make.
+    fn report(&self, path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+        let action = if is_up_to_date(path, content) {
+            "unchanged"
+        } else if path.symlink_metadata().is_ok() {
+            "overwrite"
+        } else {
+            "create"
+        };
+        println!("{:<9} {}", action, path.display());
+
+        if self.diff && action != "unchanged" {
+            print_diff(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Without `--deploy` the destination is simply overwritten. Deploying
+    /// moves whatever was there to `<destination>.pal-backup` the first time
+    /// and records the destination so `pal undeploy` can restore it.
+    fn write_target(&mut self, rendered: &Path, destination: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let up_to_date = match self.deploy {
+            #[cfg(unix)]
+            Some(Deploy::Symlink) => fs::read_link(destination).is_ok_and(|target| target == rendered),
+            _ => is_up_to_date(destination, content),
+        };
+        if up_to_date && (self.deploy.is_none() || self.deployed.iter().any(|d| d == destination)) {
+            return Ok(())
+        }
+
+        let Some(deploy) = self.deploy else {
+            fs::write(destination, content)?;
+            return copy_metadata(rendered, destination)
+        };
+
+        let exists = destination.symlink_metadata().is_ok();
+        if !self.deployed.iter().any(|d| d == destination) {
+            if exists {
+                fs::rename(destination, backup_path(destination))?;
+            }
+            let mut manifest = OpenOptions::new().create(true).append(true).open(&self.manifest_path)?;
+            writeln!(manifest, "{}", destination.display())?;
+            self.deployed.push(destination.to_path_buf());
+        } else if exists {
+            fs::remove_file(destination)?;
+        }
+
+        match deploy {
+            #[cfg(unix)]
+            Deploy::Symlink => std::os::unix::fs::symlink(rendered, destination),
+            #[cfg(not(unix))]
+            Deploy::Symlink => fs::copy(rendered, destination).map(|_| ()),
+            Deploy::Copy    => fs::copy(rendered, destination).map(|_| ()),
+        }
+    }
+}
 
-        let out_path = cache_path.join(entry.file_name());
-        fs::write(out_path, content)?;
+/// Shows `content` against the current `path` as a unified diff.
+fn print_diff(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let old = if path.exists() { path } else { Path::new("/dev/null") };
+    let mut diff = Command::new("diff")
+            .arg("-u")
+            .arg("--label").arg(path)
+            .arg("--label").arg(path)
+            .arg(old)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| std::io::Error::other(format!("could not run diff; {}", e)))?;
+    if let Some(mut stdin) = diff.stdin.take() {
+        stdin.write_all(content)?;
     }
+    diff.wait()?;
     Ok(())
 }
 
-fn parse_template(template: PathBuf, colorscheme: &Colorscheme, format: OutputFormat) -> Result<String, std::io::Error> {
-    let content = fs::read_to_string(template)?;
-    let mut result = String::new();
+fn is_up_to_date(path: &Path, content: &[u8]) -> bool {
+    return fs::read(path).is_ok_and(|old| old == content)
+}
+
+/// Gives a rendered file the template's permissions and modification time,
+/// so executable scripts stay executable.
+fn copy_metadata(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    let metadata = fs::metadata(from)?;
+    // The mtime goes first, a read-only template would keep it from being set
+    fs::File::options().write(true).open(to)?.set_modified(metadata.modified()?)?;
+    return fs::set_permissions(to, metadata.permissions())
+}
+
+fn backup_path(destination: &Path) -> PathBuf {
+    let mut backup = destination.as_os_str().to_owned();
+    backup.push(".pal-backup");
+    return PathBuf::from(backup)
+}
+
+fn read_manifest(manifest_path: &Path) -> Vec<PathBuf> {
+    return fs::read_to_string(manifest_path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(PathBuf::from)
+            .collect()
+}
+
+/// Removes deployed files and puts their backups back.
+pub fn undeploy(cache_path: &Path) -> Result<(), std::io::Error> {
+    let manifest_path = cache_path.join(DEPLOY_MANIFEST);
+    for destination in read_manifest(&manifest_path) {
+        if destination.symlink_metadata().is_ok() {
+            fs::remove_file(&destination)?;
+        }
+        let backup = backup_path(&destination);
+        if backup.exists() {
+            fs::rename(&backup, &destination)?;
+        }
+    }
+    if manifest_path.exists() {
+        fs::remove_file(manifest_path)?;
+    }
+    Ok(())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/")
+        && let Ok(home) = env::var("HOME") {
+        return Path::new(&home).join(rest)
+    }
+    return PathBuf::from(path)
+}
+
+/// Reads `key = "value"` lines; `#` starts a comment and `[sections]` are ignored.
+fn read_pairs(path: &Path) -> Result<Vec<(String, String)>, std::io::Error> {
+    if !path.exists() {
+        return Ok(Vec::new())
+    }
+
+    let unquote = |s: &str| s.trim().trim_matches('"').to_string();
+    let pairs = fs::read_to_string(path)?
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('['))
+            .filter_map(|l| {
+                let (key, value) = l.split_once('=')?;
+                Some((unquote(key), unquote(strip_comment(value))))
+            })
+            .collect();
+    Ok(pairs)
+}
+
+/// Cuts a trailing ` # comment` off a value, leaving `#` inside quotes alone.
+fn strip_comment(value: &str) -> &str {
+    let mut quoted = false;
+    let mut previous = ' ';
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted && previous.is_whitespace() => return &value[..i],
+            _ => {}
+        }
+        previous = c;
+    }
+    return value
+}
+
+/// Maps templates to destinations, `~` expands to the home directory.
+fn read_targets(targets_path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
+    let targets = read_pairs(targets_path)?
+            .into_iter()
+            .map(|(template, destination)| (PathBuf::from(template), expand_home(&destination)))
+            .collect();
+    Ok(targets)
+}
+
+fn with_user_variables(config_path: &Path, variables: &[(String, String)]) -> Result<Vec<(String, String)>, std::io::Error> {
+    let mut variables = variables.to_vec();
+    variables.extend(read_pairs(&config_path.join(VARIABLES_FILE))?
+            .into_iter()
+            .map(|(key, value)| (format!("var.{}", key), value)));
+    Ok(variables)
+}
+
+/// Renders a single template, for `pal render`, without writing anything.
+pub fn render_template(content: &str, config_path: &Path, colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat) -> Result<String, std::io::Error> {
+    let variables = with_user_variables(config_path, variables)?;
+    return Ok(render_content(content, colorscheme, &variables, format))
+}
+
+/// `variables` are plain text placeholders such as `@wallpaper`, resolved
+/// before any color, along with the user's own from `variables.toml`.
+/// Subdirectories are mirrored under the cache dir and templates listed in
+/// `targets.toml` are also written to their destination.
+/// `exports` are written to the cache dir after the templates, by file name,
+/// and can be given destinations the same way. With `--dry-run` nothing is
+/// written, each file is reported instead. Returns the exports that changed,
+/// with their destination.
+pub fn process_template_files(config_path: PathBuf, cache_path: PathBuf, colorscheme: &Colorscheme, variables: &[(String, String)], conf: &Args, exports: &[(&str, Vec<u8>)]) -> Result<Vec<(String, Option<PathBuf>)>, std::io::Error> {
+    let manifest_path = cache_path.join(DEPLOY_MANIFEST);
+    let variables = with_user_variables(&config_path, variables)?;
+
+    let mut renderer = Renderer { colorscheme:   colorscheme,
+                              variables:     &variables,
+                              format:        conf.format,
+                              targets:       read_targets(&config_path.join(TARGETS_FILE))?,
+                              deploy:        conf.deploy,
+                              deployed:      read_manifest(&manifest_path),
+                              manifest_path: manifest_path,
+                              dry_run:       conf.dry_run,
+                              diff:          conf.diff };
+    // Only missing on a dry run, which doesn't create it
+    if config_path.is_dir() {
+        renderer.render_dir(&config_path, &cache_path, Path::new(""))?;
+    }
+
+    let mut changed = Vec::new();
+    for (name, content) in exports {
+        if renderer.write_output(None, &cache_path.join(name), Path::new(name), content)? {
+            let destination = renderer.targets.iter().find(|(template, _)| template == Path::new(name)).map(|(_, d)| d.clone());
+            changed.push((name.to_string(), destination));
+        }
+    }
+    Ok(changed)
+}
+
+enum Token {
+    Text(String),
+    Placeholder(String),
+}
+
+enum Node {
+    Text(String),
+    Placeholder(String),
+    If { condition: String, then: Vec<Node>, otherwise: Vec<Node> },
+    For { name: String, body: Vec<Node>, otherwise: Vec<Node> },
+}
+
+fn tokenize(content: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
     let mut placeholder = String::new();
     let mut is_inside = 0;
 
@@ -34,52 +352,736 @@ fn parse_template(template: PathBuf, colorscheme: &Colorscheme, format: OutputFo
                 placeholder.clear();
             },
             (0, _) => {
-                result.push(c);
+                text.push(c);
             },
             (1, '`') => {
-                if let Some(repl) = resolve(&placeholder, colorscheme, format) {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+                tokens.push(Token::Placeholder(placeholder.clone()));
+                is_inside = 0
+            }
+            (1, _) => placeholder.push(c),
+            _ => {}
+        }
+    }
+
+    if is_inside == 1 {
+        text.push('`');
+        text.push_str(&placeholder);
+    }
+    tokens.push(Token::Text(text));
+    return tokens
+}
+
+fn is_block_tag(placeholder: &str) -> bool {
+    return placeholder.starts_with("@if ")
+        || placeholder.starts_with("@for ")
+        || placeholder == "@else"
+        || placeholder == "@end"
+}
+
+/// Builds the block structure up to the `@else`/`@end` closing the current
+/// block, which is returned alongside. A block tag alone on its line takes
+/// its indentation and line break with it, `skip_newline` carries that over
+/// into the block that follows the tag.
+fn parse_nodes(tokens: &[Token], i: &mut usize, mut skip_newline: bool) -> (Vec<Node>, Option<(String, bool)>) {
+    let mut nodes = Vec::new();
+    // Whether everything on the current line so far is whitespace
+    let mut blank_line = skip_newline || *i == 0;
+
+    while *i < tokens.len() {
+        let token = &tokens[*i];
+        *i += 1;
+
+        match token {
+            Token::Text(text) => {
+                let text = if skip_newline { text.strip_prefix('\n').unwrap_or(text) } else { text };
+                skip_newline = false;
+                blank_line = match text.rsplit_once('\n') {
+                    Some((_, last_line)) => last_line.trim().is_empty(),
+                    None => blank_line && text.trim().is_empty(),
+                };
+                nodes.push(Node::Text(text.to_string()));
+            }
+            Token::Placeholder(placeholder) if is_block_tag(placeholder.trim()) => {
+                let ends_line = match tokens.get(*i) {
+                    Some(Token::Text(text)) => text.starts_with('\n') || (text.is_empty() && *i + 1 == tokens.len()),
+                    _ => false,
+                };
+                let standalone = ends_line && blank_line;
+                if standalone && let Some(Node::Text(text)) = nodes.last_mut() {
+                    let indent = text.rsplit('\n').next().unwrap_or("").len();
+                    text.truncate(text.len() - indent);
+                }
+
+                let tag = placeholder.trim();
+                if tag == "@else" || tag == "@end" {
+                    return (nodes, Some((tag.to_string(), standalone)))
+                }
+                let (body, mut closing) = parse_nodes(tokens, i, standalone);
+                let mut otherwise = Vec::new();
+                if let Some(("@else", else_standalone)) = closing.as_ref().map(|(t, s)| (t.as_str(), *s)) {
+                    (otherwise, closing) = parse_nodes(tokens, i, else_standalone);
+                }
+                skip_newline = closing.is_some_and(|(_, s)| s);
+                blank_line = skip_newline;
+
+                nodes.push(match tag.strip_prefix("@if ") {
+                    Some(condition) => Node::If { condition: condition.to_string(), then: body, otherwise },
+                    None => Node::For { name: tag["@for ".len()..].trim().to_string(), body, otherwise },
+                });
+            }
+            Token::Placeholder(placeholder) => {
+                skip_newline = false;
+                blank_line = false;
+                nodes.push(Node::Placeholder(placeholder.clone()));
+            }
+        }
+    }
+    return (nodes, None)
+}
+
+/// Conditions are `light`, `dark`, `monochrome`, a variable that must be
+/// non-empty, or `name == value` / `name != value`, optionally negated with `not`.
+fn evaluate(condition: &str, colorscheme: &Colorscheme, variables: &[(String, String)]) -> bool {
+    let condition = condition.trim();
+    if let Some(rest) = condition.strip_prefix("not ") {
+        return !evaluate(rest, colorscheme, variables)
+    }
+
+    let variable = |name: &str| {
+        let name = name.trim().trim_start_matches('@');
+        variables.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    };
+    if let Some((name, value)) = condition.split_once("!=") {
+        return variable(name) != Some(value.trim())
+    }
+    if let Some((name, value)) = condition.split_once("==") {
+        return variable(name) == Some(value.trim())
+    }
+
+    let light = colorscheme.background.luminance > colorscheme.foreground.luminance;
+    match condition.trim_start_matches('@') {
+        "light"      => light,
+        "dark"       => !light,
+        "monochrome" => colorscheme.monochrome,
+        name         => variable(name).is_some_and(|v| !v.is_empty()),
+    }
+}
+
+fn render_nodes(nodes: &[Node], colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat, result: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => result.push_str(text),
+            Node::Placeholder(placeholder) => {
+                if let Some(repl) = resolve(placeholder, colorscheme, variables, format) {
                     result.push_str(&repl);
                 } else {
                     result.push('`');
-                    result.push_str(&placeholder);
+                    result.push_str(placeholder);
                     result.push('`');
                 }
-                is_inside = 0
             }
-            (1, _) => placeholder.push(c),
+            Node::If { condition, then, otherwise } => {
+                let branch = if evaluate(condition, colorscheme, variables) { then } else { otherwise };
+                render_nodes(branch, colorscheme, variables, format, result);
+            }
+            Node::For { name, body, otherwise } => {
+                if colorscheme.palette.is_empty() {
+                    render_nodes(otherwise, colorscheme, variables, format, result);
+                }
+                // The loop color shadows any other color of the same name
+                let mut scope = colorscheme.clone();
+                let mut variables = variables.to_vec();
+                variables.insert(0, ("i".to_string(), String::new()));
+                scope.named.insert(0, (name.clone(), Color::default()));
+                for (i, c) in colorscheme.palette.iter().enumerate() {
+                    variables[0].1 = i.to_string();
+                    scope.named[0].1 = *c;
+                    render_nodes(body, &scope, &variables, format, result);
+                }
+            }
+        }
+    }
+}
+
+fn parse_template(template: PathBuf, colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat) -> Result<String, std::io::Error> {
+    let content = fs::read_to_string(template)?;
+    Ok(render_content(&content, colorscheme, variables, format))
+}
+
+/// Reads a leading `#! pal format=rgb` line, which overrides `-f` for the
+/// template and is left out of the output.
+fn front_matter(content: &str, format: OutputFormat) -> (&str, OutputFormat) {
+    let Some(directive) = content.strip_prefix("#! pal ") else {
+        return (content, format)
+    };
+    let (line, body) = directive.split_once('\n').unwrap_or((directive, ""));
+
+    let mut format = format;
+    for setting in line.split_whitespace() {
+        if let Some(("format", name)) = setting.split_once('=')
+            && let Some(setting_format) = OutputFormat::from_name(name) {
+            format = setting_format;
+        } else {
+            eprintln!("Warning: ignoring unknown template setting '{}'", setting);
+        }
+    }
+    return (body, format)
+}
+
+fn render_content(content: &str, colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat) -> String {
+    let (content, format) = front_matter(content, format);
+    let tokens = tokenize(content);
+
+    let mut i = 0;
+    let mut nodes = Vec::new();
+    // A stray `@else`/`@end` is kept as text and parsing carries on after it
+    loop {
+        let (mut parsed, closing) = parse_nodes(&tokens, &mut i, false);
+        nodes.append(&mut parsed);
+        match closing {
+            Some((tag, _)) => nodes.push(Node::Text(format!("`{}`", tag))),
+            None           => break,
+        }
+    }
+
+    let mut result = String::new();
+    render_nodes(&nodes, colorscheme, variables, format, &mut result);
+    return result
+}
+
+/// Renders a `.tera` template with the colors, already formatted, and the
+/// text variables as context. User variables are grouped under `var`.
+#[cfg(feature = "tera")]
+fn render_tera(content: &str, colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat) -> Result<String, std::io::Error> {
+    use std::collections::BTreeMap;
+    use std::error::Error;
+
+    let mut context = tera::Context::new();
+    context.insert("background", &format_color(&colorscheme.background, None, format));
+    context.insert("foreground", &format_color(&colorscheme.foreground, None, format));
+    context.insert("colors", &colorscheme.palette.iter().map(|c| format_color(c, None, format)).collect::<Vec<_>>());
+    for (name, c) in &colorscheme.named {
+        context.insert(name, &format_color(c, None, format));
+    }
+    context.insert("light", &(colorscheme.background.luminance > colorscheme.foreground.luminance));
+    context.insert("monochrome", &colorscheme.monochrome);
+
+    let mut user = BTreeMap::new();
+    for (name, value) in variables {
+        match name.strip_prefix("var.") {
+            Some(name) => { user.insert(name, value); }
+            None => context.insert(name, value),
+        }
+    }
+    context.insert("var", &user);
+
+    return tera::Tera::one_off(content, &context, false).map_err(|e| {
+        // Tera keeps the useful part of the message in the error's sources
+        let mut message = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        std::io::Error::other(message)
+    })
+}
+
+#[cfg(not(feature = "tera"))]
+fn render_tera(_content: &str, _colorscheme: &Colorscheme, _variables: &[(String, String)], _format: OutputFormat) -> Result<String, std::io::Error> {
+    return Err(std::io::Error::other("pal was built without the tera feature"))
+}
+
+/// Named colors that every scheme has, or that some methods and options add.
+const ROLE_COLORS: [&str; 24] = [
+    "accent", "accent2", "error", "warning", "success", "info",
+    "crust", "mantle", "surface0", "surface1", "surface2",
+    "overlay0", "overlay1", "overlay2", "subtext0", "subtext1",
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+const VIBRANT_COLORS: [&str; 6] = ["vibrant", "dark_vibrant", "light_vibrant", "muted", "dark_muted", "light_muted"];
+const MATERIAL_PALETTES: [&str; 6] = ["primary", "secondary", "tertiary", "surface", "surface_variant", "error"];
+const MATERIAL_TONES: [u8; 13] = [0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 95, 99, 100];
+const METADATA_VARIABLES: [&str; 5] = ["wallpaper", "method", "saturation", "generated_at", "checksum"];
+const PALETTE_SIZE: usize = 16;
+const FUNCTIONS: [&str; 3] = ["mix", "contrast_text", "complement"];
+
+fn is_known_color(name: &str) -> bool {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if ROLE_COLORS.contains(&name) || VIBRANT_COLORS.contains(&name) {
+        return true
+    }
+    if let Some(index) = name.strip_prefix("base").filter(|i| i.len() == 2) {
+        return u8::from_str_radix(index, 16).is_ok_and(|i| i <= 0x0F)
+    }
+    // Ramps from `--ramps`, e.g. color4_l2
+    if let Some(rest) = name.strip_prefix("color")
+        && let Some((index, step)) = rest.split_once('_')
+        && (step.starts_with('l') || step.starts_with('d')) {
+        return is_number(index) && is_number(&step[1..])
+    }
+    return name.rsplit_once('_').is_some_and(|(palette, tone)| {
+        MATERIAL_PALETTES.contains(&palette) && tone.parse::<u8>().is_ok_and(|t| MATERIAL_TONES.contains(&t))
+    })
+}
+
+/// Returns what is wrong with a placeholder, if anything. `scope` holds the
+/// variables and loop colors that are defined where it appears.
+fn check_placeholder(placeholder: &str, scope: &[String]) -> Option<String> {
+    if scope.iter().any(|n| Some(n.as_str()) == placeholder.strip_prefix('@')) {
+        return None
+    }
+
+    let (name, modifiers) = split_modifiers(placeholder);
+    if let (function, Some(args)) = parse_call(name) {
+        if !FUNCTIONS.contains(&function.trim_start_matches('@')) {
+            return Some(format!("unknown function `{}`", function))
+        }
+        if let Some(problem) = split_args(args).iter().filter(|a| a.starts_with('@')).find_map(|a| check_placeholder(a, scope)) {
+            return Some(problem)
+        }
+    } else if let Some(problem) = check_color(placeholder, scope) {
+        return Some(problem)
+    }
+
+    // The modifiers are checked by resolving them on a blank scheme
+    let blank = Colorscheme { palette:    Vec::new(),
+                              background: Color::default(),
+                              foreground: Color::default(),
+                              named:      Vec::new(),
+                              monochrome: false };
+    let probe = std::iter::once("@background").chain(modifiers).collect::<Vec<_>>().join(".");
+    if resolve(&probe, &blank, &[], OutputFormat::HEX).is_none() {
+        return Some(format!("invalid modifier in `{}`", placeholder))
+    }
+    return None
+}
+
+fn check_color(placeholder: &str, scope: &[String]) -> Option<String> {
+    let (name, _) = split_modifiers(placeholder);
+    let bare = name.trim_start_matches('@');
+    if let Some(index) = bare.strip_prefix("color").and_then(|i| i.parse::<usize>().ok())
+        && index >= PALETTE_SIZE {
+        return Some(format!("`{}` is out of range, the palette has colors 0-{}", name, PALETTE_SIZE - 1))
+    }
+    let known = scope.iter().any(|n| n == bare)
+        || ["background", "foreground"].contains(&bare)
+        || bare.strip_prefix("color").is_some_and(|i| i.parse::<usize>().is_ok())
+        || is_known_color(bare);
+    if !known {
+        return Some(format!("unknown placeholder `{}`", placeholder))
+    }
+    return None
+}
+
+/// Reports problems in one template as `path:line: message`.
+fn lint_file(path: &Path, variables: &[String]) -> Result<usize, std::io::Error> {
+    let content = fs::read_to_string(path)?;
+    let mut problems = Vec::new();
+    // Open blocks with the line they start on, loops also define their color
+    let mut blocks: Vec<(String, usize)> = Vec::new();
+    let mut scope = variables.to_vec();
+
+    let mut line = 1;
+    let mut open: Option<(usize, String)> = None;
+    for c in content.chars() {
+        match (&mut open, c) {
+            (None, '`') => open = Some((line, String::new())),
+            (None, _) => {}
+            (Some(_), '`') => {
+                let Some((start, placeholder)) = open.take() else { continue };
+                let placeholder = placeholder.trim();
+                if !placeholder.starts_with('@') {
+                    continue
+                }
+                if let Some(name) = placeholder.strip_prefix("@for ") {
+                    scope.push(name.trim().to_string());
+                    scope.push("i".to_string());
+                    blocks.push(("@for".to_string(), start));
+                } else if placeholder.starts_with("@if ") {
+                    blocks.push(("@if".to_string(), start));
+                } else if placeholder == "@else" || placeholder == "@end" {
+                    match blocks.last() {
+                        None => problems.push((start, format!("`{}` without `@if` or `@for`", placeholder))),
+                        Some((tag, _)) if placeholder == "@end" => {
+                            if tag == "@for" {
+                                scope.truncate(scope.len() - 2);
+                            }
+                            blocks.pop();
+                        }
+                        _ => {}
+                    }
+                } else if let Some(problem) = check_placeholder(placeholder, &scope) {
+                    problems.push((start, problem));
+                }
+            }
+            (Some((_, placeholder)), _) => placeholder.push(c),
+        }
+        if c == '\n' {
+            line += 1;
+        }
+    }
+
+    if let Some((start, _)) = open {
+        problems.push((start, "unterminated backtick".to_string()));
+    }
+    for (tag, start) in blocks {
+        problems.push((start, format!("`{}` is never closed with `@end`", tag)));
+    }
+
+    problems.sort_by_key(|(line, _)| *line);
+    for (line, problem) in &problems {
+        println!("{}:{}: {}", path.display(), line, problem);
+    }
+    Ok(problems.len())
+}
+
+fn lint_dir(dir: &Path, relative: &Path, variables: &[String]) -> Result<usize, std::io::Error> {
+    let mut problems = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let relative = relative.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            if relative != Path::new(PYWAL_DIR) {
+                problems += lint_dir(&path, &relative, variables)?;
+            }
+        } else if path.is_file()
+            && relative != Path::new(TARGETS_FILE)
+            && relative != Path::new(VARIABLES_FILE)
+            && path.extension().is_none_or(|e| e != "tera") {
+            problems += lint_file(&path, variables)?;
+        }
+    }
+    Ok(problems)
+}
+
+/// Checks every template for unknown placeholders, out of range palette
+/// indices, unterminated backticks and unbalanced blocks. Returns the number
+/// of problems found, each is printed as `path:line: message`.
+pub fn lint(config_path: &Path) -> Result<usize, std::io::Error> {
+    let mut variables: Vec<String> = METADATA_VARIABLES.iter().map(|v| v.to_string()).collect();
+    variables.extend(read_pairs(&config_path.join(VARIABLES_FILE))?
+            .into_iter()
+            .map(|(key, _)| format!("var.{}", key)));
+    return lint_dir(config_path, Path::new(""), &variables)
+}
+
+/// Resolves a pywal placeholder such as `color4`, `background.strip` or
+/// `color1.lighten(0.2)`. Formats follow pywal rather than pal: `rgb` is a
+/// bare `r,g,b` and `red`/`green`/`blue` are 0-1.
+fn resolve_pywal(expression: &str, colorscheme: &Colorscheme, variables: &[(String, String)]) -> Option<String> {
+    if let Some((_, value)) = variables.iter().find(|(n, _)| n == expression) {
+        return Some(value.clone())
+    }
+    if expression == "alpha" {
+        return Some("100".to_string())
+    }
+
+    let (name, modifiers) = split_modifiers(expression);
+    let name = if name == "cursor" { "foreground" } else { name };
+    let mut color = lookup(&format!("@{}", name), colorscheme)?;
+    let hex = |c: &Color| format!("{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+    let Some((last, modifiers)) = modifiers.split_last() else {
+        return Some(format!("#{}", hex(&color)))
+    };
+
+    // pywal lightens and darkens by blending towards white and black
+    let mut blend = |modifier: &str| {
+        let (call, arg) = parse_call(modifier);
+        let amount = arg?.trim().parse::<f32>().ok()?.clamp(0.0, 1.0);
+        let towards = match call {
+            "lighten" => Color::from_hex("#ffffff")?,
+            "darken"  => Color::from_hex("#000000")?,
+            _ => return None,
+        };
+        color = color.mix(&towards, amount);
+        Some(())
+    };
+    for modifier in modifiers {
+        blend(modifier)?;
+    }
+    let formatted = match *last {
+        "hex"   => format!("#{}", hex(&color)),
+        "strip" => hex(&color),
+        "rgb"   => format!("{},{},{}", color.r, color.g, color.b),
+        "rgba"  => format!("rgba({},{},{},1.0)", color.r, color.g, color.b),
+        "xrgba" => format!("{:02x}/{:02x}/{:02x}/ff", color.r, color.g, color.b),
+        "alpha" => format!("[100]#{}", hex(&color)),
+        "red"   => format!("{:.3}", color.r as f32 / 255.0),
+        "green" => format!("{:.3}", color.g as f32 / 255.0),
+        "blue"  => format!("{:.3}", color.b as f32 / 255.0),
+        modifier => {
+            blend(modifier)?;
+            format!("#{}", hex(&color))
+        }
+    };
+    return Some(formatted)
+}
+
+/// Renders a template written for pywal, where `{color0}` is a placeholder
+/// and `{{`/`}}` are literal braces. Unknown placeholders are kept as they are.
+fn render_pywal(content: &str, colorscheme: &Colorscheme, variables: &[(String, String)]) -> String {
+    let mut result = String::new();
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut expression = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break
+                    }
+                    expression.push(c);
+                }
+                match resolve_pywal(expression.trim(), colorscheme, variables) {
+                    Some(repl) if closed => result.push_str(&repl),
+                    _ => {
+                        result.push('{');
+                        result.push_str(&expression);
+                        if closed { result.push('}'); }
+                    }
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    return result
+}
+
+/// Splits `@color4.lighten(20).darken(5)` into the color name and its
+/// modifiers, ignoring dots inside modifier arguments.
+fn split_modifiers(placeholder: &str) -> (&str, Vec<&str>) {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in placeholder.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '.' if depth == 0 => {
+                parts.push(&placeholder[start..i]);
+                start = i + 1;
+            }
             _ => {}
         }
     }
+    parts.push(&placeholder[start..]);
+    let name = parts.remove(0);
+    return (name, parts)
+}
 
-    if is_inside == 1 {
-        result.push('`');
-        result.push_str(&placeholder);
+/// Splits `lighten(20)` into `lighten` and `20`.
+fn parse_call(modifier: &str) -> (&str, Option<&str>) {
+    match modifier.split_once('(') {
+        Some((name, args)) => (name, args.strip_suffix(')')),
+        None => (modifier, None),
+    }
+}
+
+fn apply_modifier(c: Color, modifier: &str) -> Option<Color> {
+    let (name, arg) = parse_call(modifier);
+    let amount = || arg?.trim().parse::<f32>().ok();
+
+    match name {
+        "lighten" | "darken" => {
+            let amount = if name == "lighten" { amount()? } else { -amount()? };
+            let (l, chroma, hue) = c.lch();
+            Some(Color::from_lch((l + amount).clamp(0.0, 100.0), chroma, hue))
+        }
+        "rotate" => {
+            let (l, chroma, hue) = c.lch();
+            Some(Color::from_lch(l, chroma, (hue + amount()?).rem_euclid(360.0)))
+        }
+        _ => None,
+    }
+}
+
+/// Splits function arguments on the commas that are not inside parentheses.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
     }
+    parts.push(args[start..].trim());
+    return parts
+}
+
+/// A color argument, a placeholder or `#rrggbb`, which may itself carry
+/// color modifiers.
+fn color_of(expression: &str, colorscheme: &Colorscheme) -> Option<Color> {
+    let (name, modifiers) = split_modifiers(expression.trim());
+    let mut color = if name.starts_with('#') { Color::from_hex(name)? } else { lookup(name, colorscheme)? };
+    for modifier in modifiers {
+        color = apply_modifier(color, modifier)?;
+    }
+    return Some(color)
+}
 
-    Ok(result)
+/// `@mix(a, b, t)` blends `t` (default 0.5) of the way from `a` to `b`.
+/// `@contrast_text(c)` is the foreground or background, whichever is more
+/// readable on `c`, `@contrast_text(c, x, y)` picks between `x` and `y`.
+/// `@complement(c)` is `c` with its hue turned half way around.
+fn call_function(function: &str, args: &str, colorscheme: &Colorscheme) -> Option<Color> {
+    let args = split_args(args);
+    let number = |arg: &str| arg.parse::<f32>().ok();
+    let more_readable = |on: Color, a: Color, b: Color| if on.contrast_ratio(&a) >= on.contrast_ratio(&b) { a } else { b };
+
+    match (function, args.as_slice()) {
+        ("@complement", [c]) => apply_modifier(color_of(c, colorscheme)?, "rotate(180)"),
+        ("@contrast_text", [c]) => Some(more_readable(color_of(c, colorscheme)?, colorscheme.foreground, colorscheme.background)),
+        ("@contrast_text", [c, a, b]) => Some(more_readable(color_of(c, colorscheme)?, color_of(a, colorscheme)?, color_of(b, colorscheme)?)),
+        ("@mix", [a, b]) => Some(color_of(a, colorscheme)?.mix(&color_of(b, colorscheme)?, 0.5)),
+        ("@mix", [a, b, t]) => Some(color_of(a, colorscheme)?.mix(&color_of(b, colorscheme)?, number(t)?.clamp(0.0, 1.0))),
+        _ => None,
+    }
 }
 
-fn resolve(placeholder: &str, colorscheme: &Colorscheme, format: OutputFormat) -> Option<String> {
-    if placeholder.starts_with("@background") {
-        return Some(format_color(&colorscheme.background, format))
+fn lookup(name: &str, colorscheme: &Colorscheme) -> Option<Color> {
+    if let (function, Some(args)) = parse_call(name) {
+        return call_function(function, args, colorscheme)
+    }
+    if let Some((_, c)) = colorscheme.named.iter().find(|(n, _)| Some(n.as_str()) == name.strip_prefix('@')) {
+        return Some(*c)
+    }
+
+    if let Some(index) = name.strip_prefix("@base").filter(|i| i.len() == 2) {
+        return usize::from_str_radix(index, 16).ok().and_then(|i| colorscheme.base16(i))
+    }
+    if name.starts_with("@background") {
+        return Some(colorscheme.background)
     } 
-    else if placeholder.starts_with("@foreground") {
-        return Some(format_color(&colorscheme.foreground, format))
+    else if name.starts_with("@foreground") {
+        return Some(colorscheme.foreground)
     }
-    else if placeholder.starts_with("@color") {
-        return placeholder[6..] 
+    else if let Some(index) = name.strip_prefix("@color") {
+        return index
                 .parse::<usize>()
                 .ok()
                 .and_then(|i| colorscheme.palette.get(i))
-                .map(|c| format_color(c, format))
+                .copied()
     } else {
         return None
     }
 }
 
-fn format_color(c: &Color, format: OutputFormat) -> String {
-    match format {
-        OutputFormat::HEX => format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
-        OutputFormat::RGB => format!("rgb({},{},{})", c.r, c.g, c.b),
+/// Resolves `@name` with optional modifiers, e.g. `@color4.lighten(20)`.
+/// Lightness modifiers move CIE L* by the given amount, `rotate` turns the
+/// hue by the given degrees, `alpha(0-1)` adds
+/// transparency to the output and `hex`/`rgb`/`rgba`/`hsl`/`hsv`/`float` override the `-f` format,
+/// `strip` prints hex without the leading `#`.
+/// `r`/`g`/`b` print a single channel as 0-255, `rf`/`gf`/`bf` as 0-1.
+fn resolve(placeholder: &str, colorscheme: &Colorscheme, variables: &[(String, String)], format: OutputFormat) -> Option<String> {
+    if let Some((_, value)) = variables.iter().find(|(n, _)| Some(n.as_str()) == placeholder.strip_prefix('@')) {
+        return Some(value.clone())
+    }
+
+    let (name, modifiers) = split_modifiers(placeholder);
+    let mut color = lookup(name, colorscheme)?;
+    let mut alpha = None;
+    let mut format = format;
+    let mut with_alpha = false;
+    let mut channel = None;
+    let mut strip = false;
+    for modifier in modifiers {
+        match parse_call(modifier) {
+            ("alpha", Some(arg)) => alpha = Some(arg.trim().parse::<f32>().ok()?.clamp(0.0, 1.0)),
+            ("hex", None)        => format = OutputFormat::HEX,
+            ("rgb", None)        => format = OutputFormat::RGB,
+            ("rgba", None)       => (format, with_alpha) = (OutputFormat::RGB, true),
+            ("strip", None)      => (format, strip) = (OutputFormat::HEX, true),
+            ("hsl", None)        => format = OutputFormat::HSL,
+            ("hsv", None)        => format = OutputFormat::HSV,
+            ("float", None)      => format = OutputFormat::FLOAT,
+            ("r" | "g" | "b" | "rf" | "gf" | "bf", None) => channel = Some(modifier),
+            _ => color = apply_modifier(color, modifier)?,
+        }
+    }
+    if with_alpha {
+        alpha = alpha.or(Some(1.0));
+    }
+    if let Some(channel) = channel {
+        let value = match &channel[..1] {
+            "r" => color.r,
+            "g" => color.g,
+            _   => color.b,
+        };
+        return Some(if channel.ends_with('f') { format!("{:.3}", value as f32 / 255.0) } else { value.to_string() })
+    }
+    let formatted = format_color(&color, alpha, format);
+    if strip {
+        return Some(formatted.trim_start_matches('#').to_string())
+    }
+    return Some(formatted)
+}
+
+pub fn format_color(c: &Color, alpha: Option<f32>, format: OutputFormat) -> String {
+    match (format, alpha) {
+        // Colors in JSON are hex strings
+        (OutputFormat::HEX | OutputFormat::JSON, None)    => format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+        (OutputFormat::HEX | OutputFormat::JSON, Some(a)) => format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, (a * 255.0).round() as u8),
+        (OutputFormat::RGB, None)    => format!("rgb({},{},{})", c.r, c.g, c.b),
+        (OutputFormat::RGB, Some(a)) => format!("rgba({},{},{},{})", c.r, c.g, c.b, a),
+        (OutputFormat::HSL, alpha) => {
+            let (h, s, l) = c.hsl();
+            match alpha {
+                None    => format!("hsl({:.0}, {:.0}%, {:.0}%)", h, s * 100.0, l * 100.0),
+                Some(a) => format!("hsla({:.0}, {:.0}%, {:.0}%, {})", h, s * 100.0, l * 100.0, a),
+            }
+        }
+        (OutputFormat::HSV, alpha) => {
+            let (h, s, v) = c.hsv();
+            match alpha {
+                None    => format!("hsv({:.0}, {:.0}%, {:.0}%)", h, s * 100.0, v * 100.0),
+                Some(a) => format!("hsva({:.0}, {:.0}%, {:.0}%, {})", h, s * 100.0, v * 100.0, a),
+            }
+        }
+        (OutputFormat::FLOAT, alpha) => {
+            let rgb = format!("{:.3}, {:.3}, {:.3}", c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0);
+            match alpha {
+                None    => rgb,
+                Some(a) => format!("{}, {:.3}", rgb, a),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_outside_quotes_only() {
+        assert_eq!(strip_comment(r##" "#fff #comment"  # trailing"##), r##" "#fff #comment"  "##);
+        assert_eq!(strip_comment(r##" "Foo #2""##), r##" "Foo #2""##);
+        assert_eq!(strip_comment(" x # c"), " x ");
+    }
+
+    #[test]
+    fn hsv_keeps_alpha() {
+        let c = Color::from_hex("#ff0000").unwrap();
+        assert_eq!(format_color(&c, None, OutputFormat::HSV), "hsv(0, 100%, 100%)");
+        assert_eq!(format_color(&c, Some(0.5), OutputFormat::HSV), "hsva(0, 100%, 100%, 0.5)");
     }
-}
\ No newline at end of file
+}